@@ -2,17 +2,30 @@ use bech32::{FromBase32, ToBase32};
 use chrono::{DateTime, Datelike, Timelike};
 use pallas::crypto::hash::Hasher;
 use pallas::ledger::addresses::Address;
-use pallas::ledger::addresses::ByronAddress;
 use pallas::ledger::addresses::StakeAddress;
+use pallas::ledger::primitives::alonzo;
+use pallas::ledger::primitives::conway;
+use pallas::ledger::primitives::StakeCredential;
 use pallas::ledger::primitives::ToCanonicalJson;
 use pallas::ledger::traverse::wellknown::*;
+use pallas::ledger::traverse::ComputeHash;
 use pallas::ledger::traverse::MultiEraBlock;
+use pallas::ledger::traverse::MultiEraCert;
 use pallas::ledger::traverse::MultiEraOutput;
 use pallas::ledger::traverse::MultiEraTx;
 use pallas::ledger::traverse::MultiEraWithdrawals;
+use pallas::ledger::traverse::OriginalHash;
 use pgrx::prelude::*;
+use pgrx::{
+    GucContext, GucFlags, GucRegistry, GucSetting, InOutFuncs, PgMemoryContexts, StringInfo,
+};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::ffi::CStr;
 use std::ops::Deref;
+use std::rc::Rc;
+use std::thread::LocalKey;
 
 pgrx::pg_module_magic!();
 
@@ -21,984 +34,6361 @@ fn hello_extension() -> &'static str {
     "Hello, extension"
 }
 
-#[pg_extern(immutable)]
-fn block_tx_count(block_cbor: &[u8]) -> i32 {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return -1,
-    };
+/// A Cardano transaction stored as its raw CBOR bytes.
+///
+/// Values can only be built from bytes that decode as a `MultiEraTx`, so a
+/// `cardano_tx` column is guaranteed to hold a valid transaction. The text
+/// representation is the hex-encoded CBOR.
+#[allow(non_camel_case_types)]
+#[derive(PostgresType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[inoutfuncs]
+pub struct cardano_tx(#[serde(with = "serde_bytes")] Vec<u8>);
+
+impl cardano_tx {
+    fn from_cbor(cbor: Vec<u8>) -> Self {
+        if let Err(err) = MultiEraTx::decode(&cbor) {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_BINARY_REPRESENTATION,
+                format!("invalid cardano_tx: {}", err)
+            );
+        }
 
-    block.tx_count() as i32
+        cardano_tx(cbor)
+    }
 }
 
-#[pg_extern(immutable)]
-fn block_era(block_cbor: &[u8]) -> i32 {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return -1,
-    };
+impl InOutFuncs for cardano_tx {
+    fn input(input: &CStr) -> Self {
+        cardano_tx::from_cbor(hex_input(input_text(input), "cardano_tx"))
+    }
 
-    let block_era_as_u16: u16 = block.era().into();
-    block_era_as_u16.into()
+    fn output(&self, buffer: &mut StringInfo) {
+        buffer.push_str(&hex::encode(&self.0));
+    }
 }
 
-#[pg_extern(immutable)]
-fn block_txs_cbor(block_cbor: &[u8]) -> Vec<Vec<u8>> {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return vec![],
-    };
+/// A Cardano block stored as its raw CBOR bytes.
+///
+/// Values can only be built from bytes that decode as a `MultiEraBlock`. The
+/// text representation is the hex-encoded CBOR.
+#[allow(non_camel_case_types)]
+#[derive(PostgresType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[inoutfuncs]
+pub struct cardano_block(#[serde(with = "serde_bytes")] Vec<u8>);
+
+impl cardano_block {
+    fn from_cbor(cbor: Vec<u8>) -> Self {
+        if let Err(err) = MultiEraBlock::decode(&cbor) {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_BINARY_REPRESENTATION,
+                format!("invalid cardano_block: {}", err)
+            );
+        }
 
-    block.txs().into_iter().map(|tx| tx.encode()).collect()
+        cardano_block(cbor)
+    }
 }
 
-#[pg_extern(immutable)]
-fn block_number(block_cbor: &[u8]) -> i64 {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return -1,
-    };
+impl InOutFuncs for cardano_block {
+    fn input(input: &CStr) -> Self {
+        cardano_block::from_cbor(hex_input(input_text(input), "cardano_block"))
+    }
 
-    block.number() as i64
+    fn output(&self, buffer: &mut StringInfo) {
+        buffer.push_str(&hex::encode(&self.0));
+    }
 }
 
-#[pg_extern(immutable)]
-fn block_slot(block_cbor: &[u8]) -> i64 {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return -1,
-    };
-
-    block.slot() as i64
+/// A transaction output stored as its raw CBOR bytes together with the era
+/// it was produced in, so that it can be decoded without an extra argument.
+///
+/// The text representation is `<era>:<hex cbor>`, e.g. `7:8258...`.
+#[allow(non_camel_case_types)]
+#[derive(PostgresType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[inoutfuncs]
+pub struct cardano_utxo {
+    era: u16,
+    #[serde(with = "serde_bytes")]
+    cbor: Vec<u8>,
 }
 
-#[pg_extern(immutable)]
-fn block_pool_id(block_cbor: &[u8]) -> Vec<u8> {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return vec![],
-    };
-    match block.header().issuer_vkey() {
-        Some(hash) => Hasher::<224>::hash(hash).to_vec(),
-        None => vec![],
+impl cardano_utxo {
+    fn from_cbor(era: i32, cbor: Vec<u8>) -> Self {
+        let era_enum = match u16::try_from(era)
+            .ok()
+            .and_then(|x| pallas::ledger::traverse::Era::try_from(x).ok())
+        {
+            Some(x) => x,
+            None => {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                    format!("invalid cardano_utxo era: {}", era)
+                );
+            }
+        };
+
+        if let Err(err) = MultiEraOutput::decode(era_enum, &cbor) {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_BINARY_REPRESENTATION,
+                format!("invalid cardano_utxo: {}", err)
+            );
+        }
+
+        cardano_utxo {
+            era: era_enum.into(),
+            cbor,
+        }
     }
 }
 
-#[pg_extern(immutable)]
-fn block_has_pool_id(block_cbor: &[u8], pool_id: &[u8]) -> bool {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+impl InOutFuncs for cardano_utxo {
+    fn input(input: &CStr) -> Self {
+        let (era, cbor) = match input_text(input).split_once(':') {
+            Some(x) => x,
+            None => {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION,
+                    "invalid input syntax for type cardano_utxo, expected <era>:<hex>"
+                );
+            }
+        };
+
+        let era = match era.trim().parse::<i32>() {
+            Ok(x) => x,
+            Err(_) => {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION,
+                    format!("invalid era for type cardano_utxo: {}", era)
+                );
+            }
+        };
 
-    match block.header().issuer_vkey() {
-        Some(hash) => Hasher::<224>::hash(hash).to_vec() == pool_id,
-        None => false,
+        cardano_utxo::from_cbor(era, hex_input(cbor, "cardano_utxo"))
     }
-}
 
-#[pg_extern(immutable)]
-fn block_size(block_cbor: &[u8]) -> i64 {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return -1,
-    };
+    fn output(&self, buffer: &mut StringInfo) {
+        buffer.push_str(&format!("{}:{}", self.era, hex::encode(&self.cbor)));
+    }
+}
 
-    block.size() as i64
+/// A multi-asset value: an amount of lovelace plus amounts of native assets.
+///
+/// The value is stored as CBOR with the ledger `value` shape, either a coin
+/// or `[coin, { policy_id => { asset_name => quantity } }]`, except that
+/// quantities may be negative so that burns and differences can be
+/// represented. Values are normalized on input (zero quantities dropped, keys
+/// sorted, duplicate keys rejected) so that equal values have equal bytes.
+/// The text representation is the hex-encoded CBOR.
+#[allow(non_camel_case_types)]
+#[derive(PostgresType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[inoutfuncs]
+pub struct cardano_value(#[serde(with = "serde_bytes")] Vec<u8>);
+
+/// The decoded form of a `cardano_value`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct ValueAmounts {
+    coin: i128,
+    assets: std::collections::BTreeMap<Vec<u8>, std::collections::BTreeMap<Vec<u8>, i128>>,
 }
 
-#[pg_extern(immutable)]
-fn block_epoch(block_cbor: &[u8], network_id: i64) -> i64 {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return -1,
-    };
+impl ValueAmounts {
+    fn from_output(output: &MultiEraOutput) -> Self {
+        let mut value = ValueAmounts {
+            coin: output.value().coin() as i128,
+            ..Default::default()
+        };
 
-    let genesis = match GenesisValues::from_magic(network_id as u64) {
-        Some(x) => x,
-        None => return -1,
-    };
+        for policy in output.value().assets() {
+            for asset in policy.assets() {
+                value.add_asset(policy.policy().as_ref(), asset.name(), asset.any_coin());
+            }
+        }
 
-    block.epoch(&genesis).0 as i64
-}
+        value
+    }
 
-#[pg_extern(immutable)]
-fn block_slot_as_time(block_cbor: &[u8], network_id: i64) -> pgrx::Timestamp {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return (-1).into(),
-    };
+    fn from_mints(tx: &MultiEraTx) -> Self {
+        let mut value = ValueAmounts::default();
 
-    let genesis = match GenesisValues::from_magic(network_id as u64) {
-        Some(x) => x,
-        None => return (-1).into(),
-    };
+        for policy in tx.mints() {
+            for asset in policy.assets() {
+                value.add_asset(policy.policy().as_ref(), asset.name(), asset.any_coin());
+            }
+        }
 
-    let seconds = block.wallclock(&genesis) as i64;
+        value
+    }
 
-    let naive_datetime = DateTime::from_timestamp(seconds, 0)
-        .unwrap_or_else(|| DateTime::from_timestamp(0, 0).unwrap());
+    fn add_asset(&mut self, policy_id: &[u8], asset_name: &[u8], quantity: i128) {
+        let assets = self.assets.entry(policy_id.to_vec()).or_default();
+        let total = assets.entry(asset_name.to_vec()).or_default();
+        *total = match total.checked_add(quantity) {
+            Some(x) => x,
+            None => value_out_of_range(),
+        };
 
-    let year = naive_datetime.year();
-    let month = naive_datetime.month() as u8;
-    let day = naive_datetime.day() as u8;
-    let hour = naive_datetime.hour() as u8;
-    let minute = naive_datetime.minute() as u8;
-    let second = naive_datetime.second() as f64;
+        if *total == 0 {
+            assets.remove(asset_name);
+        }
 
-    Timestamp::new(year, month, day, hour, minute, second).unwrap()
-}
+        if assets.is_empty() {
+            self.assets.remove(policy_id);
+        }
+    }
 
-#[pg_extern(immutable)]
-fn block_is_epoch(block_cbor: &[u8], network_id: i64, epoch: i64) -> bool {
-    let block = match MultiEraBlock::decode(block_cbor) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+    fn checked_add(mut self, other: &ValueAmounts) -> Self {
+        self.coin = match self.coin.checked_add(other.coin) {
+            Some(x) => x,
+            None => value_out_of_range(),
+        };
 
-    let genesis = match GenesisValues::from_magic(network_id as u64) {
-        Some(x) => x,
-        None => return false,
-    };
+        for (policy_id, assets) in other.assets.iter() {
+            for (asset_name, quantity) in assets.iter() {
+                self.add_asset(policy_id, asset_name, *quantity);
+            }
+        }
 
-    block.epoch(&genesis).0 == epoch as u64
-}
+        self
+    }
 
-/// Returns the hash of the given transaction data.
-///
-/// # Arguments
-///
-/// * `tx_cbor` - The transaction data in CBOR format.
-///
-/// # Returns
-///
-/// The hash of the given transaction data as a string.
-///
-/// # Example
-///
-/// ```
-/// select tx_hash(body) from transactions;
-/// ```
-#[pg_extern(immutable)]
-fn tx_hash(tx_cbor: &[u8]) -> String {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return "".to_string(),
-    };
+    fn negate(mut self) -> Self {
+        self.coin = -self.coin;
+        self.assets
+            .values_mut()
+            .flat_map(|x| x.values_mut())
+            .for_each(|x| *x = -*x);
+        self
+    }
 
-    tx.hash().to_string()
-}
+    /// Whether every amount of `self` is lower than or equal to the same
+    /// amount of `other`, missing assets counting as zero.
+    fn le(&self, other: &ValueAmounts) -> bool {
+        let difference = other.clone().checked_add(&self.clone().negate());
+
+        difference.coin >= 0
+            && difference
+                .assets
+                .values()
+                .flat_map(|x| x.values())
+                .all(|x| *x >= 0)
+    }
 
-#[pg_extern(immutable)]
-fn tx_inputs(tx_cbor: &[u8]) -> Vec<Option<String>> {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return vec![],
-    };
+    fn quantity(&self, policy_id: &[u8], asset_name: &[u8]) -> i128 {
+        self.assets
+            .get(policy_id)
+            .and_then(|x| x.get(asset_name))
+            .copied()
+            .unwrap_or_default()
+    }
 
-    tx.consumes()
-        .iter()
-        .map(|i| Some(format!("{}#{}", i.hash(), i.index())))
-        .collect::<Vec<Option<String>>>()
-}
+    fn decode(cbor: &[u8]) -> Result<Self, String> {
+        use pallas::codec::minicbor::{self, bytes::ByteVec, data::Int, data::Type};
+        use pallas::codec::utils::KeyValuePairs;
 
-#[allow(clippy::type_complexity)]
-#[pg_extern(immutable)]
-fn tx_outputs(
-    tx_cbor: &[u8],
-) -> TableIterator<
-    'static,
-    (
-        name!(output_index, i32),
-        name!(address, Option<String>),
-        name!(lovelace, pgrx::AnyNumeric),
-        name!(assets, pgrx::Json),
-        name!(datum, pgrx::Json),
-        name!(cbor, Vec<u8>),
-    ),
-> {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return TableIterator::new(std::iter::empty()),
-    };
+        // Decoded as pairs rather than maps so that duplicate keys, which a
+        // map would silently merge, can be rejected.
+        type Assets = KeyValuePairs<ByteVec, KeyValuePairs<ByteVec, Int>>;
 
-    let outputs_data = tx
-        .produces()
-        .iter()
-        .map(|(i, o)| {
-            (
-                *i as i32,
-                output_to_address_string(o),
-                AnyNumeric::from(o.value().coin()),
-                pgrx::Json(
-                    serde_json::to_value(
-                        o.value()
-                            .assets()
-                            .iter()
-                            .map(|asset| {
-                                let policy_id = hex::encode(asset.policy().as_ref());
-                                let assets: HashMap<String, i128> = asset
-                                    .assets()
-                                    .iter()
-                                    .map(|a| (hex::encode(a.name()), a.any_coin()))
-                                    .collect();
-
-                                (policy_id, assets)
-                            })
-                            .collect::<HashMap<_, _>>(),
-                    )
-                    .unwrap(),
-                ),
-                match o.datum() {
-                    Some(d) => match d {
-                        pallas::ledger::primitives::conway::PseudoDatumOption::Hash(h) => {
-                            pgrx::Json(serde_json::json!(h))
-                        }
-                        pallas::ledger::primitives::conway::PseudoDatumOption::Data(d) => {
-                            pgrx::Json(d.unwrap().deref().to_json())
-                        }
-                    },
-                    None => pgrx::Json(serde_json::json!(null)),
-                },
-                o.encode(),
-            )
-        })
-        .collect::<Vec<_>>();
+        let mut decoder = minicbor::Decoder::new(cbor);
 
-    TableIterator::new(outputs_data)
-}
+        let (coin, assets) = match decoder.datatype().map_err(|x| x.to_string())? {
+            Type::Array | Type::ArrayIndef => decoder
+                .decode::<(Int, Assets)>()
+                .map_err(|x| x.to_string())?,
+            _ => (
+                decoder.decode::<Int>().map_err(|x| x.to_string())?,
+                Assets::from(vec![]),
+            ),
+        };
 
-#[pg_extern(immutable)]
-fn tx_outputs_json(tx_cbor: &[u8]) -> pgrx::JsonB {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return pgrx::JsonB(serde_json::json!([])),
-    };
+        if decoder.position() != cbor.len() {
+            return Err(format!("trailing bytes at offset {}", decoder.position()));
+        }
 
-    let outputs_data: Vec<serde_json::Value> = tx
-        .produces()
-        .iter()
-        .map(|(i, o)| {
-            serde_json::json!({
-                "output_index": *i as i32,
-                "address": output_to_address_string(o),
-                "lovelace": o.value().coin().to_string(),
-                "assets": o.value().assets()
-                    .iter()
-                    .map(|asset| {
-                        let policy_id = hex::encode(asset.policy().as_ref());
-                        let assets: HashMap<String, String> = asset
-                            .assets()
-                            .iter()
-                            .map(|a| (hex::encode(a.name()), a.any_coin().to_string()))
-                            .collect();
+        let mut value = ValueAmounts {
+            coin: coin.into(),
+            ..Default::default()
+        };
 
-                        (policy_id, assets)
-                    })
-                    .collect::<HashMap<_, _>>(),
-                "datum": match o.datum() {
-                    Some(d) => match d {
-                        pallas::ledger::primitives::conway::PseudoDatumOption::Hash(h) =>
-                            serde_json::json!(h),
-                        pallas::ledger::primitives::conway::PseudoDatumOption::Data(d) =>
-                            d.unwrap().deref().to_json(),
-                    },
-                    None => serde_json::json!(null),
-                },
-            })
-        })
-        .collect();
+        let mut policy_ids = std::collections::BTreeSet::new();
 
-    pgrx::JsonB(serde_json::json!(outputs_data))
-}
+        for (policy_id, assets) in assets.iter() {
+            if !policy_ids.insert(policy_id.to_vec()) {
+                return Err(format!(
+                    "duplicate policy id {}",
+                    hex::encode(policy_id.as_slice())
+                ));
+            }
 
-#[pg_extern(immutable)]
-fn tx_is_valid(tx_cbor: &[u8]) -> bool {
-    match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x.is_valid(),
-        Err(_) => false,
+            let mut asset_names = std::collections::BTreeSet::new();
+
+            for (asset_name, quantity) in assets.iter() {
+                if !asset_names.insert(asset_name.to_vec()) {
+                    return Err(format!(
+                        "duplicate asset {}.{}",
+                        hex::encode(policy_id.as_slice()),
+                        hex::encode(asset_name.as_slice())
+                    ));
+                }
+
+                value.add_asset(policy_id, asset_name, (*quantity).into());
+            }
+        }
+
+        Ok(value)
     }
-}
 
-#[pg_extern(immutable)]
-fn tx_addresses(tx_cbor: &[u8]) -> Vec<Option<String>> {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return vec![],
-    };
+    fn encode(&self) -> Vec<u8> {
+        use pallas::codec::minicbor::{self, bytes::ByteVec, data::Int};
 
-    let outputs_data = tx
-        .outputs()
-        .iter()
-        .map(|o| output_to_address_string(o))
-        .collect::<Vec<_>>();
+        let int = |x: i128| match Int::try_from(x) {
+            Ok(x) => x,
+            Err(_) => value_out_of_range(),
+        };
 
-    outputs_data
-}
+        if self.assets.is_empty() {
+            return minicbor::to_vec(int(self.coin)).unwrap();
+        }
 
-#[pg_extern(immutable)]
-fn tx_plutus_data(tx_cbor: &[u8]) -> pgrx::JsonB {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return pgrx::JsonB(serde_json::json!([])),
-    };
+        let assets: std::collections::BTreeMap<_, std::collections::BTreeMap<_, _>> = self
+            .assets
+            .iter()
+            .map(|(policy_id, assets)| {
+                let assets = assets
+                    .iter()
+                    .map(|(name, quantity)| (ByteVec::from(name.clone()), int(*quantity)))
+                    .collect();
 
-    let plutus_data: Vec<serde_json::Value> =
-        tx.plutus_data().iter().map(|x| x.to_json()).collect();
+                (ByteVec::from(policy_id.clone()), assets)
+            })
+            .collect();
 
-    pgrx::JsonB(serde_json::json!(plutus_data))
-}
+        minicbor::to_vec((int(self.coin), assets)).unwrap()
+    }
 
-#[pg_extern(immutable)]
-fn tx_lovelace(tx_cbor: &[u8]) -> pgrx::AnyNumeric {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return AnyNumeric::from(0),
-    };
+    fn to_json(&self) -> serde_json::Value {
+        let assets: HashMap<String, HashMap<String, String>> = self
+            .assets
+            .iter()
+            .map(|(policy_id, assets)| {
+                let assets = assets
+                    .iter()
+                    .map(|(name, quantity)| (hex::encode(name), quantity.to_string()))
+                    .collect();
+
+                (hex::encode(policy_id), assets)
+            })
+            .collect();
 
-    AnyNumeric::from(tx.outputs().iter().map(|o| o.value().coin()).sum::<u64>())
+        serde_json::json!(assets)
+    }
 }
 
-#[pg_extern(immutable)]
-fn tx_fee(tx_cbor: &[u8]) -> pgrx::AnyNumeric {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return AnyNumeric::from(0),
-    };
-    let fee = match tx.fee() {
-        Some(f) => f,
-        None => return AnyNumeric::from(0),
-    };
-    AnyNumeric::from(fee)
+fn value_out_of_range() -> ! {
+    ereport!(
+        ERROR,
+        PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE,
+        "cardano_value out of range"
+    );
 }
 
-#[pg_extern(immutable)]
-fn tx_mint(tx_cbor: &[u8]) -> Option<pgrx::JsonB> {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return None,
-    };
+impl cardano_value {
+    fn from_cbor(cbor: &[u8]) -> Self {
+        cardano_value::decode(cbor).into()
+    }
 
-    let mints = tx.mints();
+    fn amounts(&self) -> ValueAmounts {
+        cardano_value::decode(&self.0)
+    }
 
-    let mint_data: HashMap<String, HashMap<String, String>> = mints
-        .iter()
-        .map(|m| {
-            let policy_id = hex::encode(m.policy().as_ref());
-            let assets: HashMap<String, String> = m
-                .assets()
-                .iter()
-                .map(|a| (hex::encode(a.name()), a.any_coin().to_string()))
-                .collect();
+    fn decode(cbor: &[u8]) -> ValueAmounts {
+        match ValueAmounts::decode(cbor) {
+            Ok(x) => x,
+            Err(err) => {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_INVALID_BINARY_REPRESENTATION,
+                    format!("invalid cardano_value: {}", err)
+                );
+            }
+        }
+    }
+}
 
-            (policy_id, assets)
-        })
-        .collect();
+impl From<ValueAmounts> for cardano_value {
+    fn from(value: ValueAmounts) -> Self {
+        cardano_value(value.encode())
+    }
+}
 
-    if mint_data.is_empty() {
-        return None;
+impl InOutFuncs for cardano_value {
+    fn input(input: &CStr) -> Self {
+        cardano_value::from_cbor(&hex_input(input_text(input), "cardano_value"))
     }
 
-    Some(pgrx::JsonB(serde_json::json!(mint_data)))
+    fn output(&self, buffer: &mut StringInfo) {
+        buffer.push_str(&hex::encode(&self.0));
+    }
 }
 
-#[pg_extern(immutable)]
-fn tx_subject_amount_output(tx_cbor: &[u8], subject: &[u8]) -> pgrx::AnyNumeric {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return AnyNumeric::from(0),
-    };
-
-    const POLICY_ID_LEN: usize = 28;
-    let (policy_id, asset_name) = subject.split_at(POLICY_ID_LEN);
+/// A native asset, identified either by its policy id and asset name or by
+/// its CIP-14 fingerprint.
+///
+/// The text input accepts the policy id and asset name as a single hex
+/// string, as `<policy id hex>.<asset name hex>` or as an `asset1...`
+/// fingerprint. Fingerprints can't be reversed, so a subject built from one
+/// only keeps the fingerprint and matches assets by hashing them. The text
+/// output is the dotted form, or the fingerprint for fingerprint subjects.
+#[allow(non_camel_case_types)]
+#[derive(PostgresType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[inoutfuncs]
+pub struct asset_subject {
+    #[serde(with = "serde_bytes")]
+    policy_id: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    asset_name: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    fingerprint: Vec<u8>,
+}
 
-    let amount = tx
-        .outputs()
-        .iter()
-        .filter(|o| {
-            o.value().assets().to_vec().iter().any(|a| {
-                a.policy().deref() == policy_id && a.assets().iter().any(|a| a.name() == asset_name)
-            })
-        })
-        .map(|o| {
-            o.value()
-                .assets()
-                .to_vec()
-                .iter()
-                .flat_map(|a| {
-                    a.assets()
-                        .iter()
-                        .filter(|a| a.name() == asset_name)
-                        .map(|a| a.any_coin())
-                        .collect::<Vec<_>>()
-                })
-                .next()
-                .unwrap_or(0)
-        })
-        .sum::<i128>();
+const POLICY_ID_LEN: usize = 28;
+const MAX_ASSET_NAME_LEN: usize = 32;
 
-    AnyNumeric::from(amount)
+/// The CIP-14 fingerprint of an asset: the blake2b-160 hash of its policy id
+/// followed by its asset name.
+fn asset_fingerprint_hash(policy_id: &[u8], asset_name: &[u8]) -> Vec<u8> {
+    Hasher::<160>::hash(&[policy_id, asset_name].concat()).to_vec()
 }
 
-#[pg_extern(immutable)]
-fn tx_subject_amount_mint(tx_cbor: &[u8], subject: &[u8]) -> pgrx::AnyNumeric {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return AnyNumeric::from(0),
-    };
+impl asset_subject {
+    fn from_parts(policy_id: &[u8], asset_name: &[u8]) -> Result<Self, String> {
+        if policy_id.len() != POLICY_ID_LEN {
+            return Err(format!("invalid policy id: {}", hex::encode(policy_id)));
+        }
 
-    const POLICY_ID_LEN: usize = 28;
-    let (policy_id, asset_name) = subject.split_at(POLICY_ID_LEN);
+        if asset_name.len() > MAX_ASSET_NAME_LEN {
+            return Err(format!(
+                "asset name longer than {} bytes: {}",
+                MAX_ASSET_NAME_LEN,
+                hex::encode(asset_name)
+            ));
+        }
 
-    let amount = tx
-        .mints()
-        .iter()
-        .filter(|m| {
-            m.assets()
-                .iter()
-                .any(|a| a.policy().deref() == policy_id && a.name() == asset_name)
-        })
-        .map(|m| {
-            m.assets()
-                .iter()
-                .filter(|a| a.name() == asset_name)
-                .map(|a| a.any_coin())
-                .sum::<i128>()
+        Ok(asset_subject {
+            policy_id: policy_id.to_vec(),
+            asset_name: asset_name.to_vec(),
+            fingerprint: asset_fingerprint_hash(policy_id, asset_name),
         })
-        .sum::<i128>();
+    }
 
-    AnyNumeric::from(amount)
-}
+    /// Parses the policy id followed by the asset name.
+    fn from_bytes(subject: &[u8]) -> Result<Self, String> {
+        if subject.len() < POLICY_ID_LEN {
+            return Err(format!("invalid asset subject: {}", hex::encode(subject)));
+        }
 
-#[pg_extern(immutable)]
-fn tx_withdrawals(
-    tx_cbor: &[u8],
-) -> TableIterator<
-    'static,
-    (
-        name!(stake_address, Vec<u8>),
-        name!(amount, pgrx::AnyNumeric),
-    ),
-> {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return TableIterator::new(std::iter::empty()),
-    };
+        let (policy_id, asset_name) = subject.split_at(POLICY_ID_LEN);
+        asset_subject::from_parts(policy_id, asset_name)
+    }
 
-    let withdrawals_data = match tx.withdrawals() {
-        MultiEraWithdrawals::AlonzoCompatible(w) => w
-            .iter()
-            .map(|(k, v)| (k.to_vec(), AnyNumeric::from(*v)))
-            .collect::<Vec<_>>(),
-        _ => vec![],
-    };
-    TableIterator::new(withdrawals_data)
-}
+    fn from_text(subject: &str) -> Result<Self, String> {
+        let subject = subject.trim();
+        let invalid = || format!("invalid asset subject: {}", subject);
+
+        if subject.starts_with("asset1") {
+            let fingerprint = match bech32::decode(subject) {
+                Ok((hrp, data, _)) if hrp == "asset" => Vec::from_base32(&data).ok(),
+                _ => None,
+            };
+
+            return match fingerprint {
+                Some(x) if x.len() == 20 => Ok(asset_subject {
+                    policy_id: vec![],
+                    asset_name: vec![],
+                    fingerprint: x,
+                }),
+                _ => Err(invalid()),
+            };
+        }
 
-#[pg_extern(immutable)]
-fn tx_withdrawals_json(tx_cbor: &[u8]) -> pgrx::JsonB {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return pgrx::JsonB(serde_json::json!({})),
-    };
+        match subject.split_once('.') {
+            Some((policy_id, asset_name)) => {
+                match (hex::decode(policy_id), hex::decode(asset_name)) {
+                    (Ok(policy_id), Ok(asset_name)) => {
+                        asset_subject::from_parts(&policy_id, &asset_name)
+                    }
+                    _ => Err(invalid()),
+                }
+            }
+            None => match hex::decode(subject) {
+                Ok(x) => asset_subject::from_bytes(&x),
+                Err(_) => Err(invalid()),
+            },
+        }
+    }
 
-    let withdrawals_data: HashMap<String, String> = match tx.withdrawals() {
-        MultiEraWithdrawals::AlonzoCompatible(w) => w
+    fn matches(&self, policy_id: &[u8], asset_name: &[u8]) -> bool {
+        if self.policy_id.is_empty() {
+            self.fingerprint == asset_fingerprint_hash(policy_id, asset_name)
+        } else {
+            self.policy_id == policy_id && self.asset_name == asset_name
+        }
+    }
+
+    /// The total quantity of the asset held by an output.
+    fn output_amount(&self, output: &MultiEraOutput) -> i128 {
+        output
+            .value()
+            .assets()
             .iter()
-            .map(|(k, v)| (hex::encode(k.to_vec()), v.to_string()))
-            .collect(),
-        _ => HashMap::new(),
-    };
+            .flat_map(|p| {
+                p.assets()
+                    .iter()
+                    .filter(|a| self.matches(p.policy().as_ref(), a.name()))
+                    .map(|a| a.any_coin())
+                    .collect::<Vec<_>>()
+            })
+            .sum()
+    }
 
-    pgrx::JsonB(serde_json::json!(withdrawals_data))
+    /// The quantity of the asset minted (or burned, if negative) by a
+    /// transaction.
+    fn mint_amount(&self, tx: &MultiEraTx) -> Option<i128> {
+        tx.mints()
+            .iter()
+            .flat_map(|p| {
+                p.assets()
+                    .iter()
+                    .filter(|a| self.matches(p.policy().as_ref(), a.name()))
+                    .map(|a| a.any_coin())
+                    .collect::<Vec<_>>()
+            })
+            .reduce(|a, b| a + b)
+    }
 }
 
-#[pg_extern(immutable)]
-fn tx_hash_is(tx_cbor: &[u8], hash: &[u8]) -> bool {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+impl InOutFuncs for asset_subject {
+    fn input(input: &CStr) -> Self {
+        match asset_subject::from_text(input_text(input)) {
+            Ok(x) => x,
+            Err(err) => {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION,
+                    err
+                );
+            }
+        }
+    }
 
-    tx.hash().to_vec().eq(&hash)
+    fn output(&self, buffer: &mut StringInfo) {
+        if self.policy_id.is_empty() {
+            buffer.push_str(&encode_asset_fingerprint(&self.fingerprint));
+        } else {
+            buffer.push_str(&format!(
+                "{}.{}",
+                hex::encode(&self.policy_id),
+                hex::encode(&self.asset_name)
+            ));
+        }
+    }
 }
 
-#[pg_extern(immutable)]
-fn tx_has_mint(tx_cbor: &[u8]) -> bool {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
-
-    !tx.mints().is_empty()
+fn encode_asset_fingerprint(fingerprint: &[u8]) -> String {
+    bech32::encode("asset", fingerprint.to_base32(), bech32::Variant::Bech32).unwrap()
 }
 
-#[pg_extern(immutable)]
-fn tx_has_address_output(tx_cbor: &[u8], address: &[u8]) -> bool {
-    let tx = match MultiEraTx::decode(tx_cbor) {
+fn input_text(input: &CStr) -> &str {
+    match input.to_str() {
         Ok(x) => x,
-        Err(_) => return false,
-    };
-
-    tx.outputs().iter().any(|o| {
-        o.address()
-            .ok()
-            .map(|iter_address| iter_address.to_vec().eq(address))
-            .unwrap_or(false)
-    })
+        Err(_) => {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_CHARACTER_NOT_IN_REPERTOIRE,
+                "input is not valid UTF-8"
+            );
+        }
+    }
 }
 
-#[pg_extern(immutable)]
-fn tx_has_policy_id_output(tx_cbor: &[u8], policy_id: &[u8]) -> bool {
-    let tx = match MultiEraTx::decode(tx_cbor) {
+/// Decodes the hex text representation shared by the CBOR-backed types,
+/// accepting an optional `\x` prefix so that `bytea` literals can be reused.
+fn hex_input(text: &str, type_name: &str) -> Vec<u8> {
+    match hex::decode(text.trim().trim_start_matches("\\x")) {
         Ok(x) => x,
-        Err(_) => return false,
-    };
+        Err(err) => {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION,
+                format!("invalid hex for type {}: {}", type_name, err)
+            );
+        }
+    }
+}
 
-    tx.outputs().iter().any(|o| {
-        o.value()
-            .assets()
-            .to_vec()
-            .iter()
-            .any(|a| a.policy().deref().eq(&policy_id))
-    })
+#[pg_extern(immutable, name = "cardano_tx")]
+fn bytea_to_cardano_tx(tx_cbor: &[u8]) -> cardano_tx {
+    cardano_tx::from_cbor(tx_cbor.to_vec())
 }
 
 #[pg_extern(immutable)]
-fn tx_has_policy_id_mint(tx_cbor: &[u8], policy_id: &[u8]) -> bool {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+fn cardano_tx_to_bytea(tx: cardano_tx) -> Vec<u8> {
+    tx.0
+}
 
-    tx.mints().iter().any(|m| m.policy().deref().eq(&policy_id))
+#[pg_extern(immutable, name = "cardano_block")]
+fn bytea_to_cardano_block(block_cbor: &[u8]) -> cardano_block {
+    cardano_block::from_cbor(block_cbor.to_vec())
 }
 
 #[pg_extern(immutable)]
-fn tx_has_subject_output(tx_cbor: &[u8], subject: &[u8]) -> bool {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+fn cardano_block_to_bytea(block: cardano_block) -> Vec<u8> {
+    block.0
+}
 
-    const POLICY_ID_LEN: usize = 28;
-    let (policy_id, asset_name) = subject.split_at(POLICY_ID_LEN);
+#[pg_extern(immutable, name = "cardano_utxo")]
+fn bytea_to_cardano_utxo(era: i32, utxo_cbor: &[u8]) -> cardano_utxo {
+    cardano_utxo::from_cbor(era, utxo_cbor.to_vec())
+}
 
-    tx.outputs().iter().any(|o| {
-        o.value().assets().to_vec().iter().any(|a| {
-            a.policy().deref() == policy_id && a.assets().iter().any(|a| a.name() == asset_name)
-        })
-    })
+#[pg_extern(immutable)]
+fn cardano_utxo_era(utxo: cardano_utxo) -> i32 {
+    utxo.era.into()
 }
 
 #[pg_extern(immutable)]
-fn tx_has_mint_output(tx_cbor: &[u8], subject: &[u8]) -> bool {
-    let tx = match MultiEraTx::decode(tx_cbor) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+fn cardano_utxo_to_bytea(utxo: cardano_utxo) -> Vec<u8> {
+    utxo.cbor
+}
 
-    const POLICY_ID_LEN: usize = 28;
-    let (policy_id, asset_name) = subject.split_at(POLICY_ID_LEN);
+#[pg_extern(immutable, name = "cardano_value")]
+fn bytea_to_cardano_value(value_cbor: &[u8]) -> cardano_value {
+    cardano_value::from_cbor(value_cbor)
+}
 
-    tx.mints().iter().any(|m| {
-        m.assets()
-            .iter()
-            .any(|a| a.policy().deref() == policy_id && a.name() == asset_name)
-    })
+#[pg_extern(immutable)]
+fn cardano_value_to_bytea(value: cardano_value) -> Vec<u8> {
+    value.0
 }
 
 #[pg_extern(immutable)]
-fn address_network_id(address: &[u8]) -> i64 {
-    let address = match Address::from_bytes(address) {
-        Ok(x) => x,
-        Err(_) => return -1,
-    };
+fn cardano_value_lovelace(value: cardano_value) -> pgrx::AnyNumeric {
+    AnyNumeric::from(value.amounts().coin)
+}
 
-    match address.network() {
-        Some(n) => n.value() as i64,
-        None => -1,
-    }
+#[pg_extern(immutable)]
+fn cardano_value_assets(value: cardano_value) -> pgrx::JsonB {
+    pgrx::JsonB(value.amounts().to_json())
 }
 
 #[pg_extern(immutable)]
-fn address_payment_part(address: &[u8]) -> Vec<u8> {
-    let address = match Address::from_bytes(address) {
-        Ok(x) => x,
-        Err(_) => return vec![],
-    };
+fn cardano_value_asset(
+    value: cardano_value,
+    policy_id: &[u8],
+    asset_name: &[u8],
+) -> pgrx::AnyNumeric {
+    AnyNumeric::from(value.amounts().quantity(policy_id, asset_name))
+}
 
-    let payment_part = match address {
-        Address::Shelley(a) => a.payment().to_vec(),
-        Address::Byron(_) => {
-            vec![]
-        }
-        _ => return vec![],
-    };
+#[pg_operator(immutable, parallel_safe)]
+#[opname(+)]
+#[commutator(+)]
+fn cardano_value_add(left: cardano_value, right: cardano_value) -> cardano_value {
+    left.amounts().checked_add(&right.amounts()).into()
+}
 
-    payment_part
+#[pg_operator(immutable, parallel_safe)]
+#[opname(-)]
+fn cardano_value_sub(left: cardano_value, right: cardano_value) -> cardano_value {
+    left.amounts().checked_add(&right.amounts().negate()).into()
 }
 
-#[pg_extern(immutable)]
-fn address_stake_part(address: &[u8]) -> Vec<u8> {
-    let address = match Address::from_bytes(address) {
-        Ok(x) => x,
-        Err(_) => return vec![],
-    };
+// values are normalized when built, so equal values have equal bytes
+#[pg_operator(immutable, parallel_safe)]
+#[opname(=)]
+#[commutator(=)]
+#[negator(<>)]
+fn cardano_value_eq(left: cardano_value, right: cardano_value) -> bool {
+    left == right
+}
 
-    let stake_part = match address {
-        Address::Shelley(a) => a.delegation().to_vec(),
-        Address::Byron(_) => {
-            vec![]
-        }
-        _ => return vec![],
-    };
+#[pg_operator(immutable, parallel_safe)]
+#[opname(<>)]
+#[commutator(<>)]
+#[negator(=)]
+fn cardano_value_ne(left: cardano_value, right: cardano_value) -> bool {
+    left != right
+}
 
-    stake_part
+#[pg_operator(immutable, parallel_safe)]
+#[opname(<=)]
+fn cardano_value_le(left: cardano_value, right: cardano_value) -> bool {
+    left.amounts().le(&right.amounts())
 }
 
-#[pg_extern(immutable)]
-fn address_to_bytes(address: String) -> Vec<u8> {
-    let address = match Address::from_bech32(&address) {
+#[pg_extern(immutable, name = "asset_subject")]
+fn asset_subject_from_parts(policy_id: &[u8], asset_name: &[u8]) -> asset_subject {
+    match asset_subject::from_parts(policy_id, asset_name) {
         Ok(x) => x,
-        Err(_) => return vec![],
-    };
+        Err(err) => {
+            ereport!(ERROR, PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, err);
+        }
+    }
+}
 
-    address.to_vec()
+// `sum(cardano_value)` adds values componentwise. The combine function lets
+// Postgres split the aggregation across parallel workers.
+extension_sql!(
+    r#"
+CREATE CAST (bytea AS cardano_value) WITH FUNCTION cardano_value(bytea) AS ASSIGNMENT;
+CREATE CAST (cardano_value AS bytea) WITH FUNCTION cardano_value_to_bytea(cardano_value) AS IMPLICIT;
+
+CREATE AGGREGATE sum (cardano_value) (
+    SFUNC = cardano_value_add,
+    STYPE = cardano_value,
+    COMBINEFUNC = cardano_value_add,
+    PARALLEL = SAFE
+);
+"#,
+    name = "cardano_value_sum",
+    requires = [
+        bytea_to_cardano_value,
+        cardano_value_to_bytea,
+        cardano_value_add
+    ]
+);
+
+// `cardano_tx` and `cardano_block` are implicitly castable to `bytea`, so every
+// `tx_*` and `block_*` function accepts them directly. Casting from `bytea`
+// validates the CBOR, which also applies when inserting into typed columns.
+extension_sql!(
+    r#"
+CREATE CAST (bytea AS cardano_tx) WITH FUNCTION cardano_tx(bytea) AS ASSIGNMENT;
+CREATE CAST (cardano_tx AS bytea) WITH FUNCTION cardano_tx_to_bytea(cardano_tx) AS IMPLICIT;
+CREATE CAST (bytea AS cardano_block) WITH FUNCTION cardano_block(bytea) AS ASSIGNMENT;
+CREATE CAST (cardano_block AS bytea) WITH FUNCTION cardano_block_to_bytea(cardano_block) AS IMPLICIT;
+"#,
+    name = "cardano_casts",
+    requires = [
+        bytea_to_cardano_tx,
+        cardano_tx_to_bytea,
+        bytea_to_cardano_block,
+        cardano_block_to_bytea
+    ]
+);
+
+/// What functions return when their input can't be decoded, set through the
+/// `mumak.on_decode_error` GUC.
+#[allow(non_camel_case_types)]
+#[derive(PostgresGucEnum, Clone, Copy, PartialEq, Eq, Debug)]
+enum DecodeErrorMode {
+    /// Return NULL.
+    null,
+    /// Return a function specific placeholder (`-1`, `''`, `false`, `0`, an
+    /// empty array or set), which was the only behaviour before the GUC existed.
+    sentinel,
+    /// Raise an error carrying the underlying decode error message.
+    error,
 }
 
-#[pg_extern(immutable)]
-fn address_to_bech32(address_bytes: &[u8]) -> String {
-    let address = match Address::from_bytes(address_bytes) {
-        Ok(x) => x,
-        Err(_) => return String::new(),
-    };
+static ON_DECODE_ERROR: GucSetting<DecodeErrorMode> =
+    GucSetting::<DecodeErrorMode>::new(DecodeErrorMode::sentinel);
+
+#[allow(non_snake_case)]
+#[pg_guard]
+pub extern "C" fn _PG_init() {
+    GucRegistry::define_enum_guc(
+        "mumak.on_decode_error",
+        "What mumak functions return when their input can't be decoded.",
+        "'null' returns NULL, 'sentinel' returns a function specific placeholder value and 'error' raises an error.",
+        &ON_DECODE_ERROR,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+}
 
-    match address.to_bech32() {
-        Ok(x) => x,
-        // @TODO: this is not bech32 though?
-        Err(_) => ByronAddress::from_bytes(address_bytes).unwrap().to_base58(),
-    }
+/// Resolves the result of a function whose CBOR (or address) input failed to
+/// decode according to `mumak.on_decode_error`.
+fn on_decode_error<T>(err: impl std::fmt::Display, sentinel: T) -> Option<T> {
+    on_error(
+        PgSqlErrorCode::ERRCODE_INVALID_BINARY_REPRESENTATION,
+        format!("failed to decode input: {}", err),
+        sentinel,
+    )
 }
 
-#[pg_extern(immutable)]
-fn address_to_stake_part_bech32(address_bytes: &[u8]) -> String {
-    let address = match Address::from_bytes(address_bytes) {
-        Ok(addr) => addr,
-        Err(_) => return String::new(),
-    };
+/// Resolves the result of a function that received an argument it can't work
+/// with (e.g. an unknown network magic) according to `mumak.on_decode_error`.
+fn on_invalid_argument<T>(message: String, sentinel: T) -> Option<T> {
+    on_error(
+        PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+        message,
+        sentinel,
+    )
+}
 
-    match address {
-        Address::Shelley(a) => StakeAddress::try_from(a)
-            .map(|stake_addr| stake_addr.to_bech32().unwrap_or_else(|_| String::new()))
-            .unwrap_or_else(|_| String::new()),
-        Address::Byron(_) => String::new(),
-        _ => String::new(),
+fn on_error<T>(code: PgSqlErrorCode, message: String, sentinel: T) -> Option<T> {
+    match ON_DECODE_ERROR.get() {
+        DecodeErrorMode::null => None,
+        DecodeErrorMode::sentinel => Some(sentinel),
+        DecodeErrorMode::error => {
+            ereport!(ERROR, code, message);
+        }
     }
 }
 
-#[pg_extern(immutable)]
-fn stake_part_to_bech32(stake_part_bytes: &[u8]) -> String {
-    let stake_part = match Address::from_bytes(stake_part_bytes) {
-        Ok(x) => x,
-        Err(_) => return String::new(),
-    };
+/// Number of decoded structures of each kind kept alive at any time.
+const DECODE_CACHE_CAPACITY: usize = 8;
 
-    stake_part.to_bech32().unwrap_or_default()
+/// A decoded structure together with the CBOR bytes it borrows from.
+struct Decoded<T: 'static> {
+    // `value` borrows from `cbor`, so it must be declared (and dropped) first.
+    value: T,
+    cbor: Vec<u8>,
+    era: u16,
 }
 
-#[pg_extern(immutable)]
-fn utxo_address(era: i32, utxo_cbor: &[u8]) -> Option<Vec<u8>> {
-    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
-        Ok(x) => x,
-        Err(_) => return None,
+/// Implements `get` for the decoded structures, which are stored with a
+/// `'static` lifetime they don't really have.
+macro_rules! impl_decoded_get {
+    ($($ty:ident),*) => {
+        $(
+            impl Decoded<$ty<'static>> {
+                fn get(&self) -> &$ty<'_> {
+                    // SAFETY: the `'static` lifetime of `value` is a lie told
+                    // by `decode_cached`; it really borrows from `cbor`, which
+                    // is owned by `self`, never mutated and dropped after
+                    // `value`. Shortening it to the borrow of `self` is
+                    // therefore sound, and the transmute is needed because the
+                    // pallas types are invariant over their lifetime.
+                    unsafe { std::mem::transmute::<&$ty<'static>, &$ty<'_>>(&self.value) }
+                }
+            }
+        )*
     };
+}
 
-    let output = match MultiEraOutput::decode(era_enum, utxo_cbor) {
-        Ok(x) => x,
-        Err(_) => return Some(vec![]),
-    };
+impl_decoded_get!(MultiEraTx, MultiEraBlock, MultiEraOutput);
 
-    output.address().ok().map(|address| address.to_vec())
+/// Recently decoded structures, each tagged with the memory context that was
+/// current when it was decoded.
+///
+/// Every function evaluated against the same row runs in the same per-tuple
+/// memory context, so `SELECT tx_hash(cbor), tx_fee(cbor) ...` decodes `cbor`
+/// once. Entries are evicted when PostgreSQL resets or deletes their context.
+struct DecodeCache<T: 'static> {
+    entries: Vec<(pg_sys::MemoryContext, Rc<Decoded<T>>)>,
 }
 
-#[pg_extern(immutable)]
-fn utxo_has_policy_id(era: i32, utxo_cbor: &[u8], policy_id: &[u8]) -> bool {
-    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+impl<T: 'static> DecodeCache<T> {
+    const fn new() -> Self {
+        DecodeCache {
+            entries: Vec::new(),
+        }
+    }
 
-    let output = match MultiEraOutput::decode(era_enum, utxo_cbor) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+    fn get(&self, era: u16, cbor: &[u8]) -> Option<Rc<Decoded<T>>> {
+        self.entries
+            .iter()
+            .find(|(_, x)| x.era == era && x.cbor == cbor)
+            .map(|(_, x)| x.clone())
+    }
 
-    output
-        .value()
-        .assets()
-        .to_vec()
-        .iter()
-        .any(|a| a.policy().deref().eq(&policy_id))
-}
+    fn insert(&mut self, context: pg_sys::MemoryContext, decoded: Rc<Decoded<T>>) {
+        if self.entries.len() >= DECODE_CACHE_CAPACITY {
+            self.entries.remove(0);
+        }
 
-#[pg_extern(immutable)]
-fn utxo_has_address(era: i32, utxo_cbor: &[u8], address: &[u8]) -> bool {
-    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+        self.entries.push((context, decoded));
+    }
 
-    let output = match MultiEraOutput::decode(era_enum, utxo_cbor) {
-        Ok(x) => x,
-        Err(_) => return false,
-    };
+    fn evict(&mut self, context: pg_sys::MemoryContext) {
+        self.entries.retain(|(x, _)| *x != context);
+    }
+}
 
-    output
-        .address()
-        .ok()
-        .map(|iter_address| iter_address.to_vec().eq(&address))
-        .unwrap_or_else(|| false)
+thread_local! {
+    static TX_CACHE: RefCell<DecodeCache<MultiEraTx<'static>>> = const { RefCell::new(DecodeCache::new()) };
+    static BLOCK_CACHE: RefCell<DecodeCache<MultiEraBlock<'static>>> = const { RefCell::new(DecodeCache::new()) };
+    static UTXO_CACHE: RefCell<DecodeCache<MultiEraOutput<'static>>> = const { RefCell::new(DecodeCache::new()) };
+    static CACHE_SCOPES: RefCell<Vec<pg_sys::MemoryContext>> = const { RefCell::new(Vec::new()) };
 }
 
-#[pg_extern(immutable)]
-fn utxo_lovelace(era: i32, utxo_cbor: &[u8]) -> pgrx::AnyNumeric {
-    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
-        Ok(x) => x,
-        Err(_) => return AnyNumeric::from(0),
-    };
+/// Evicts every cache entry decoded in a memory context when it is reset.
+struct DecodeCacheScope(pg_sys::MemoryContext);
 
-    let output = match MultiEraOutput::decode(era_enum, utxo_cbor) {
-        Ok(x) => x,
-        Err(_) => return AnyNumeric::from(0),
-    };
+impl Drop for DecodeCacheScope {
+    fn drop(&mut self) {
+        let context = self.0;
 
-    AnyNumeric::from(output.value().coin())
+        TX_CACHE.with(|c| c.borrow_mut().evict(context));
+        BLOCK_CACHE.with(|c| c.borrow_mut().evict(context));
+        UTXO_CACHE.with(|c| c.borrow_mut().evict(context));
+        CACHE_SCOPES.with(|c| c.borrow_mut().retain(|x| *x != context));
+    }
 }
 
-#[pg_extern(immutable)]
-fn utxo_policy_id_asset_names(
-    era: i32,
-    utxo_cbor: &[u8],
-    policy_id: &[u8],
-) -> SetOfIterator<'static, Vec<u8>> {
-    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
-        Ok(x) => x,
-        Err(_) => return SetOfIterator::new(std::iter::empty()),
-    };
+fn decode_cached<T: 'static>(
+    cache: &'static LocalKey<RefCell<DecodeCache<T>>>,
+    era: u16,
+    cbor: &[u8],
+    decode: impl FnOnce(&'static [u8]) -> Result<T, pallas::ledger::traverse::Error>,
+) -> Result<Rc<Decoded<T>>, pallas::ledger::traverse::Error> {
+    if let Some(x) = cache.with(|c| c.borrow().get(era, cbor)) {
+        return Ok(x);
+    }
 
-    let output = match MultiEraOutput::decode(era_enum, utxo_cbor) {
-        Ok(x) => x,
-        Err(_) => return SetOfIterator::new(std::iter::empty()),
-    };
+    // The argument may point into a shared buffer or a detoasted copy that is
+    // freed before the entry is evicted, so the entry has to own its bytes.
+    // Copying once per miss is cheaper than decoding twice to avoid it.
+    let cbor = cbor.to_vec();
 
-    let asset_names = output
-        .value()
-        .assets()
-        .to_vec()
-        .iter()
-        .filter(|a| a.policy().deref().eq(&policy_id))
-        .flat_map(|a| {
-            a.assets()
-                .iter()
-                .map(|a| a.name().to_vec())
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
+    // SAFETY: the decoded value only outlives this function inside `Decoded`,
+    // which owns the (never mutated) bytes for as long as it exists and only
+    // hands out references bound to its own lifetime.
+    let bytes: &'static [u8] = unsafe { std::slice::from_raw_parts(cbor.as_ptr(), cbor.len()) };
+    let value = decode(bytes)?;
 
-    SetOfIterator::new(asset_names)
-}
+    let decoded = Rc::new(Decoded { value, cbor, era });
 
-#[pg_extern(immutable)]
-fn utxo_asset_values(
-    era: i32,
+    let mut context = PgMemoryContexts::CurrentMemoryContext;
+    let context_ptr = context.value();
+
+    let is_new_scope = CACHE_SCOPES.with(|c| {
+        let mut scopes = c.borrow_mut();
+        let is_new = !scopes.contains(&context_ptr);
+        if is_new {
+            scopes.push(context_ptr);
+        }
+        is_new
+    });
+
+    if is_new_scope {
+        context.leak_and_drop_on_delete(DecodeCacheScope(context_ptr));
+    }
+
+    cache.with(|c| c.borrow_mut().insert(context_ptr, decoded.clone()));
+
+    Ok(decoded)
+}
+
+fn decode_tx(
+    tx_cbor: &[u8],
+) -> Result<Rc<Decoded<MultiEraTx<'static>>>, pallas::ledger::traverse::Error> {
+    decode_cached(&TX_CACHE, 0, tx_cbor, MultiEraTx::decode)
+}
+
+fn decode_block(
+    block_cbor: &[u8],
+) -> Result<Rc<Decoded<MultiEraBlock<'static>>>, pallas::ledger::traverse::Error> {
+    decode_cached(&BLOCK_CACHE, 0, block_cbor, MultiEraBlock::decode)
+}
+
+fn decode_utxo(
+    era: pallas::ledger::traverse::Era,
     utxo_cbor: &[u8],
-) -> TableIterator<
-    'static,
-    (
-        name!(policy_id, Vec<u8>),
-        name!(asset_name, Vec<u8>),
-        name!(amount, pgrx::AnyNumeric),
-    ),
-> {
-    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
-        Ok(x) => x,
-        Err(_) => return TableIterator::new(std::iter::empty()),
+) -> Result<Rc<Decoded<MultiEraOutput<'static>>>, pallas::ledger::traverse::Error> {
+    decode_cached(&UTXO_CACHE, era.into(), utxo_cbor, |x| {
+        MultiEraOutput::decode(era, x).map_err(pallas::ledger::traverse::Error::invalid_cbor)
+    })
+}
+
+extension_sql!(
+    r#"
+CREATE TYPE decode_error AS (
+    message text,
+    byte_offset bigint,
+    era integer,
+    major_type integer
+);
+"#,
+    name = "decode_error_type",
+);
+
+/// Why a piece of CBOR failed to decode, as reported by `tx_decode_error` and
+/// `block_decode_error`.
+struct DecodeDiagnostic {
+    message: String,
+    offset: Option<usize>,
+    era: Option<pallas::ledger::traverse::Era>,
+}
+
+impl DecodeDiagnostic {
+    fn from_minicbor(
+        era: pallas::ledger::traverse::Era,
+        err: pallas::codec::minicbor::decode::Error,
+        cbor: &[u8],
+    ) -> Self {
+        // minicbor doesn't report a position for truncated input, but the
+        // decoder necessarily got all the way to the end of it.
+        let offset = if err.is_end_of_input() {
+            Some(cbor.len())
+        } else {
+            err.position()
+        };
+
+        DecodeDiagnostic {
+            message: err.to_string(),
+            offset,
+            era: Some(era),
+        }
+    }
+
+    fn into_composite(self, cbor: &[u8]) -> pgrx::composite_type!('static, "decode_error") {
+        // The major type lives in the top three bits of the initial byte of
+        // the data item the decoder choked on.
+        let major_type = self
+            .offset
+            .and_then(|x| cbor.get(x))
+            .map(|x| (x >> 5) as i32);
+        let era = self.era.map(|x| u16::from(x) as i32);
+
+        let mut record = PgHeapTuple::new_composite_type("decode_error").unwrap();
+        record.set_by_name("message", self.message).unwrap();
+        record
+            .set_by_name("byte_offset", self.offset.map(|x| x as i64))
+            .unwrap();
+        record.set_by_name("era", era).unwrap();
+        record.set_by_name("major_type", major_type).unwrap();
+        record
+    }
+}
+
+/// Decodes a transaction against every era `MultiEraTx::decode` tries and
+/// keeps the attempt that got the furthest into the CBOR, which is most likely
+/// the era the transaction was meant to be.
+fn tx_decode_diagnostic(tx_cbor: &[u8]) -> Option<DecodeDiagnostic> {
+    use pallas::ledger::traverse::Era;
+
+    let mut best: Option<DecodeDiagnostic> = None;
+
+    for era in [Era::Conway, Era::Babbage, Era::Alonzo, Era::Byron] {
+        let err = match MultiEraTx::decode_for_era(era, tx_cbor) {
+            Ok(_) => return None,
+            Err(err) => DecodeDiagnostic::from_minicbor(era, err, tx_cbor),
+        };
+
+        if best.as_ref().is_none_or(|x| err.offset > x.offset) {
+            best = Some(err);
+        }
+    }
+
+    best
+}
+
+/// Decodes a block for the era announced by its tag. Blocks are decoded
+/// directly rather than through `MultiEraBlock::decode`, which discards the
+/// position of the error.
+fn block_decode_diagnostic(block_cbor: &[u8]) -> Option<DecodeDiagnostic> {
+    use pallas::codec::minicbor;
+    use pallas::ledger::primitives::{babbage, byron};
+    use pallas::ledger::traverse::probe::{block_era, Outcome};
+    use pallas::ledger::traverse::Era;
+
+    let (era, result) = match block_era(block_cbor) {
+        Outcome::EpochBoundary => (
+            Era::Byron,
+            minicbor::decode::<(u16, byron::MintedEbBlock)>(block_cbor).map(|_| ()),
+        ),
+        Outcome::Matched(Era::Byron) => (
+            Era::Byron,
+            minicbor::decode::<(u16, byron::MintedBlock)>(block_cbor).map(|_| ()),
+        ),
+        Outcome::Matched(era @ (Era::Shelley | Era::Allegra | Era::Mary | Era::Alonzo)) => (
+            era,
+            minicbor::decode::<(u16, alonzo::MintedBlock)>(block_cbor).map(|_| ()),
+        ),
+        Outcome::Matched(Era::Babbage) => (
+            Era::Babbage,
+            minicbor::decode::<(u16, babbage::MintedBlock)>(block_cbor).map(|_| ()),
+        ),
+        Outcome::Matched(era) => (
+            era,
+            minicbor::decode::<(u16, conway::MintedBlock)>(block_cbor).map(|_| ()),
+        ),
+        Outcome::Inconclusive => {
+            return Some(DecodeDiagnostic {
+                message: "expected an array starting with a known era tag".to_string(),
+                offset: Some(0),
+                era: None,
+            })
+        }
     };
 
-    let output = match MultiEraOutput::decode(era_enum, utxo_cbor) {
+    result
+        .err()
+        .map(|err| DecodeDiagnostic::from_minicbor(era, err, block_cbor))
+}
+
+/// Explains why a transaction can't be decoded.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// NULL if the transaction decodes, otherwise a `decode_error` record with the
+/// error message, the byte offset at which decoding failed, the era that got
+/// the furthest and the CBOR major type found at that offset.
+///
+/// # Example
+///
+/// ```
+/// select slot from txs where tx_decode_error(cbor) is not null;
+/// ```
+#[pg_extern(immutable, requires = ["decode_error_type"])]
+fn tx_decode_error(tx_cbor: &[u8]) -> Option<pgrx::composite_type!('static, "decode_error")> {
+    tx_decode_diagnostic(tx_cbor).map(|x| x.into_composite(tx_cbor))
+}
+
+/// Explains why a block can't be decoded.
+///
+/// # Arguments
+///
+/// * `block_cbor` - The block data in CBOR format.
+///
+/// # Returns
+///
+/// NULL if the block decodes, otherwise a `decode_error` record with the error
+/// message, the byte offset at which decoding failed, the era announced by the
+/// block tag and the CBOR major type found at that offset.
+///
+/// # Example
+///
+/// ```
+/// select (block_decode_error(cbor)).* from blocks where block_decode_error(cbor) is not null;
+/// ```
+#[pg_extern(immutable, requires = ["decode_error_type"])]
+fn block_decode_error(block_cbor: &[u8]) -> Option<pgrx::composite_type!('static, "decode_error")> {
+    block_decode_diagnostic(block_cbor).map(|x| x.into_composite(block_cbor))
+}
+
+#[pg_extern(immutable)]
+fn block_tx_count(block_cbor: &[u8]) -> Option<i32> {
+    let block = match decode_block(block_cbor) {
         Ok(x) => x,
-        Err(_) => return TableIterator::new(std::iter::empty()),
+        Err(err) => return on_decode_error(err, -1),
     };
+    let block = block.get();
 
-    let asset_values = output
-        .value()
-        .assets()
-        .to_vec()
-        .iter()
-        .flat_map(|a| {
-            a.assets()
-                .iter()
-                .map(|a| {
-                    (
-                        a.policy().to_vec(),
-                        a.name().to_vec(),
-                        AnyNumeric::from(a.any_coin()),
-                    )
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
+    Some(block.tx_count() as i32)
+}
 
-    TableIterator::new(asset_values)
+#[pg_extern(immutable)]
+fn block_era(block_cbor: &[u8]) -> Option<i32> {
+    let block = match decode_block(block_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, -1),
+    };
+    let block = block.get();
+
+    let block_era_as_u16: u16 = block.era().into();
+    Some(block_era_as_u16.into())
 }
 
 #[pg_extern(immutable)]
-fn utxo_policy_id_asset_values(
-    era: i32,
-    utxo_cbor: &[u8],
-    policy_id: &[u8],
-) -> TableIterator<'static, (name!(asset_name, Vec<u8>), name!(amount, pgrx::AnyNumeric))> {
-    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+fn block_txs_cbor(block_cbor: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let block = match decode_block(block_cbor) {
         Ok(x) => x,
-        Err(_) => return TableIterator::new(std::iter::empty()),
+        Err(err) => return on_decode_error(err, vec![]),
     };
+    let block = block.get();
+
+    Some(block.txs().into_iter().map(|tx| tx.encode()).collect())
+}
 
-    let output = match MultiEraOutput::decode(era_enum, utxo_cbor) {
+#[pg_extern(immutable)]
+fn block_number(block_cbor: &[u8]) -> Option<i64> {
+    let block = match decode_block(block_cbor) {
         Ok(x) => x,
-        Err(_) => return TableIterator::new(std::iter::empty()),
+        Err(err) => return on_decode_error(err, -1),
     };
+    let block = block.get();
 
-    let asset_values = output
-        .value()
-        .assets()
-        .to_vec()
-        .iter()
-        .filter(|a| a.policy().deref().eq(&policy_id))
-        .flat_map(|a| {
-            a.assets()
-                .iter()
-                .map(|a| (a.name().to_vec(), AnyNumeric::from(a.any_coin())))
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
+    Some(block.number() as i64)
+}
+
+#[pg_extern(immutable)]
+fn block_slot(block_cbor: &[u8]) -> Option<i64> {
+    let block = match decode_block(block_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, -1),
+    };
+    let block = block.get();
 
-    TableIterator::new(asset_values)
+    Some(block.slot() as i64)
 }
 
 #[pg_extern(immutable)]
-fn utxo_subject_amount(era: i32, utxo_cbor: &[u8], subject: &[u8]) -> pgrx::AnyNumeric {
-    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+fn block_pool_id(block_cbor: &[u8]) -> Option<Vec<u8>> {
+    let block = match decode_block(block_cbor) {
         Ok(x) => x,
-        Err(_) => return AnyNumeric::from(0),
+        Err(err) => return on_decode_error(err, vec![]),
     };
+    let block = block.get();
+    match block.header().issuer_vkey() {
+        Some(hash) => Some(Hasher::<224>::hash(hash).to_vec()),
+        None => Some(vec![]),
+    }
+}
 
-    let output = match MultiEraOutput::decode(era_enum, utxo_cbor) {
+#[pg_extern(immutable)]
+fn block_has_pool_id(block_cbor: &[u8], pool_id: &[u8]) -> Option<bool> {
+    let block = match decode_block(block_cbor) {
         Ok(x) => x,
-        Err(_) => return AnyNumeric::from(0),
+        Err(err) => return on_decode_error(err, false),
     };
+    let block = block.get();
 
-    const POLICY_ID_LEN: usize = 28;
-    let (policy_id, asset_name) = subject.split_at(POLICY_ID_LEN);
+    match block.header().issuer_vkey() {
+        Some(hash) => Some(Hasher::<224>::hash(hash).to_vec() == pool_id),
+        None => Some(false),
+    }
+}
 
-    let amount = output
-        .value()
-        .assets()
-        .iter()
-        .filter(|a| a.policy().deref() == policy_id)
-        .flat_map(|a| {
-            a.assets()
-                .iter()
-                .filter(|a| a.name() == asset_name)
-                .map(|a| a.any_coin())
-                .collect::<Vec<_>>()
-        })
-        .next()
-        .unwrap_or(0);
+#[pg_extern(immutable)]
+fn block_size(block_cbor: &[u8]) -> Option<i64> {
+    let block = match decode_block(block_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, -1),
+    };
+    let block = block.get();
 
-    AnyNumeric::from(amount)
+    Some(block.size() as i64)
 }
 
 #[pg_extern(immutable)]
-fn utxo_plutus_data(era: i32, utxo_cbor: &[u8]) -> Option<pgrx::Json> {
-    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+fn block_epoch(block_cbor: &[u8], network_id: i64) -> Option<i64> {
+    let block = match decode_block(block_cbor) {
         Ok(x) => x,
-        Err(_) => return None,
+        Err(err) => return on_decode_error(err, -1),
     };
+    let block = block.get();
 
-    let output = MultiEraOutput::decode(era_enum, utxo_cbor).ok()?;
+    let genesis = match GenesisValues::from_magic(network_id as u64) {
+        Some(x) => x,
+        None => return on_invalid_argument(format!("unknown network magic: {}", network_id), -1),
+    };
 
-    output.datum().and_then(|datum_option| match datum_option {
-        pallas::ledger::primitives::conway::PseudoDatumOption::Hash(_) => None,
-        pallas::ledger::primitives::conway::PseudoDatumOption::Data(d) => {
-            Some(pgrx::Json(d.deref().to_json()))
-        }
-    })
+    Some(block.epoch(&genesis).0 as i64)
 }
 
 #[pg_extern(immutable)]
-fn to_bech32(hash: &[u8], hrp: &str) -> String {
-    match bech32::encode(hrp, hash.to_base32(), bech32::Variant::Bech32) {
+fn block_slot_as_time(block_cbor: &[u8], network_id: i64) -> Option<pgrx::Timestamp> {
+    let block = match decode_block(block_cbor) {
         Ok(x) => x,
-        Err(_) => "".to_string(),
-    }
+        Err(err) => return on_decode_error(err, (-1).into()),
+    };
+    let block = block.get();
+
+    let genesis = match GenesisValues::from_magic(network_id as u64) {
+        Some(x) => x,
+        None => {
+            return on_invalid_argument(
+                format!("unknown network magic: {}", network_id),
+                (-1).into(),
+            )
+        }
+    };
+
+    let seconds = block.wallclock(&genesis) as i64;
+
+    let naive_datetime = DateTime::from_timestamp(seconds, 0)
+        .unwrap_or_else(|| DateTime::from_timestamp(0, 0).unwrap());
+
+    let year = naive_datetime.year();
+    let month = naive_datetime.month() as u8;
+    let day = naive_datetime.day() as u8;
+    let hour = naive_datetime.hour() as u8;
+    let minute = naive_datetime.minute() as u8;
+    let second = naive_datetime.second() as f64;
+
+    Some(Timestamp::new(year, month, day, hour, minute, second).unwrap())
 }
 
 #[pg_extern(immutable)]
-fn from_bech32(bech32: &str) -> Vec<u8> {
-    match bech32::decode(bech32) {
-        Ok((_, data, _)) => Vec::from_base32(&data).unwrap(),
-        Err(_) => vec![],
-    }
-}
+fn block_is_epoch(block_cbor: &[u8], network_id: i64, epoch: i64) -> Option<bool> {
+    let block = match decode_block(block_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let block = block.get();
 
-fn output_to_address_string(output: &MultiEraOutput) -> Option<String> {
-    match output.address() {
-        Ok(addr) => {
-            let addr_str = addr.to_string();
-            // Truncate the address to 103 characters typical HEADER, PAYMENT PART and DELEGATION PART encoding in bech32
-            if addr_str.len() > 103 {
-                Some(addr_str[..103].to_string())
-            } else {
-                Some(addr_str)
-            }
+    let genesis = match GenesisValues::from_magic(network_id as u64) {
+        Some(x) => x,
+        None => {
+            return on_invalid_argument(format!("unknown network magic: {}", network_id), false)
         }
-        Err(_) => None,
-    }
+    };
+
+    Some(block.epoch(&genesis).0 == epoch as u64)
 }
 
-#[cfg(any(test, feature = "pg_test"))]
-#[pg_schema]
-mod tests {
-    use super::*;
+/// Returns the lovelace donated to the treasury by the valid transactions of
+/// the given block data.
+///
+/// # Arguments
+///
+/// * `block_cbor` - The block data in CBOR format.
+///
+/// # Returns
+///
+/// The sum of the treasury donations, 0 if there are none.
+///
+/// # Example
+///
+/// ```
+/// select block_epoch(body, 764824073) as epoch, sum(block_treasury_donations(body)) from blocks group by 1;
+/// ```
+#[pg_extern(immutable)]
+fn block_treasury_donations(block_cbor: &[u8]) -> Option<pgrx::AnyNumeric> {
+    let block = match decode_block(block_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+    let block = block.get();
 
-    #[pg_test]
-    fn test_hello_extension() {
-        assert_eq!("Hello, extension", crate::hello_extension());
-    }
+    // phase-2 invalid transactions only collect their collateral
+    let donations = block
+        .txs()
+        .iter()
+        .filter(|tx| tx.is_valid())
+        .filter_map(treasury_donation)
+        .sum::<u64>();
 
-    #[pg_test]
-    fn test_tx_hash() {
-        // Decoded transaction data for testing
-        const TX_DATA_HEX: &str = "84ad009282582040e50ebf0ded25391f7dd13ad2d32a8eef5a2cc76cc0e95b8bb2330c482def2f0082582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520082582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520282582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520382582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520482582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520582582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520682582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520782582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520882582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520982582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520a82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520b82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520c82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520d82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520e82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520f82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822521082582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f282252110182a300581d71071bd7f4b5e059ea90e763467cf559167b21c82ef1cb5fe34fb7a9e501821a030a32c0a3581c1cc1aceaf5c7df55e270864a60600b9f52383fe418164574ffdeeed0a14010581cc0e5564cf5786031d9053f567ec78b8383a0f2bc01318e690e0503f4a14001581cf66d78b4a3cb3d37afa0ec36461e51ecbde00f26c8f0a68f94b69880a144695553441b00000201d16e7cf2028201d818479f0000000000ff82583901da299558c70a8970781806dca93d1801ba2f3b3894227a7b284786e49baba19195b7cb8b1c6febb192cc487b5e8b96d737baddb8bb09866f1b000000015053786b021a0007a272031a07138899075820d36a2619a672494604e11bb447cbcf5231e9f2ba25c2169177edc941bd50ad6c081a0713876d0b5820de92cfe211abe2b770d253ff364362e4281c96ce70c3048b104acb5fc172ea900d8182582040e50ebf0ded25391f7dd13ad2d32a8eef5a2cc76cc0e95b8bb2330c482def2f000e81581cda299558c70a8970781806dca93d1801ba2f3b3894227a7b284786e40f011082583901da299558c70a8970781806dca93d1801ba2f3b3894227a7b284786e49baba19195b7cb8b1c6febb192cc487b5e8b96d737baddb8bb09866f1b00000001502d541d111a002dc6c0128482582032536acbfa12b80a3c570b1dac7948187dfa66992460d11542f67ba357c0fd2c0082582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520182582089f6715ff7affd8bdeff696f47d7a08bd899cc9c627483a8885f9fd3943286a100825820db7900797bf9c1235976b226d0cdbe1040d199555158bfe2bc042575f142da6100a30082825820f44ce6186d190f8776fd871d753df7ae503972e4793a2360a423d2f96021e60158400b18e4fcf4be17a531d3fd7a0320df6ba7acbff31b35118275d7ff1cb3de25523453c767af39ac1f3b435749c44af64ecbaf19ae1e23a7b4ab9c7939d653a90182582063179f731829d60aade12a1398c07b7a905cc38e7d9901850c9b186946f5ca3e58403b3932c709d9a355f8a0bb453d2722f39f82a16bb7669669f11698cacc825ce2a74b26aa31f3740a8a820829bfda3f6f3f4bbce1f045707d037df0085273a50004800591840001d87980821a001aaf3e1a315977a684000ad87980821a00011efa1a01c4794f840006d87980821a00011efa1a01c4794f840004d87980821a00011efa1a01c4794f840002d87980821a00011efa1a01c4794f840003d87980821a00011efa1a01c4794f840005d87980821a00011efa1a01c4794f840008d87980821a00011efa1a01c4794f840007d87980821a00011efa1a01c4794f840009d87980821a00011efa1a01c4794f84000ed87980821a00011efa1a01c4794f84000cd87980821a00011efa1a01c4794f84000bd87980821a00011efa1a01c4794f84000dd87980821a00011efa1a01c4794f840010d87980821a00011efa1a01c4794f84000fd87980821a00011efa1a01c4794f840011d87980821a00011efa1a01c4794ff5a0";
-        // Expected hash result for the given transaction data
-        const EXPECTED_HASH: &str =
-            "691bb954d364ac5a2fe4bafc72b43a77edee54bd4237d748547426f14f304c96";
+    Some(AnyNumeric::from(donations))
+}
 
-        let tx_cbor = hex::decode(TX_DATA_HEX).expect("Failed to decode hex string into bytes");
+/// Returns the hash of the given transaction data.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// The hash of the given transaction data as a string.
+///
+/// # Example
+///
+/// ```
+/// select tx_hash(body) from transactions;
+/// ```
+#[pg_extern(immutable)]
+fn tx_hash(tx_cbor: &[u8]) -> Option<String> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, "".to_string()),
+    };
+    let tx = tx.get();
 
-        assert_eq!(
-            EXPECTED_HASH,
-            crate::tx_hash(&tx_cbor).to_string(),
-            "The hash of the provided transaction data did not match the expected value."
-        );
-    }
+    Some(tx.hash().to_string())
+}
 
-    #[pg_test]
-    fn test_utxo_address() {
-        let utxo_hex = "825839000ba2902f70b40716d84de3d9c01ddc19b514d18f9b6911319a72900d6ee29460029464593dd53cd1435025e2e5614f60be06104c54b472eb1a68022c9f";
-        let utxo_address = utxo_address(7, &hex::decode(utxo_hex).unwrap());
-        assert_eq!("000ba2902f70b40716d84de3d9c01ddc19b514d18f9b6911319a72900d6ee29460029464593dd53cd1435025e2e5614f60be06104c54b472eb", utxo_address.map(hex::encode).unwrap());
+#[pg_extern(immutable)]
+fn tx_inputs(tx_cbor: &[u8]) -> Option<Vec<Option<String>>> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+    let tx = tx.get();
+
+    Some(
+        tx.consumes()
+            .iter()
+            .map(|i| Some(format!("{}#{}", i.hash(), i.index())))
+            .collect::<Vec<Option<String>>>(),
+    )
+}
+
+#[pg_extern(immutable)]
+fn tx_reference_inputs(tx_cbor: &[u8]) -> Option<Vec<Option<String>>> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+    let tx = tx.get();
+
+    Some(
+        tx.reference_inputs()
+            .iter()
+            .map(|i| Some(format!("{}#{}", i.hash(), i.index())))
+            .collect::<Vec<Option<String>>>(),
+    )
+}
+
+/// Returns the inputs of the given transaction data (the same ones as
+/// `tx_inputs`) as a row per input.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+/// * `consumed` - Whether to return the inputs the ledger actually spends
+///   rather than the ones in the body. They only differ for phase-2 invalid
+///   transactions, which spend their collateral inputs instead.
+///
+/// # Returns
+///
+/// A row per input with the hash of the transaction that created the output
+/// being spent and the index of that output.
+///
+/// # Example
+///
+/// ```
+/// select i.* from transactions, lateral tx_inputs_table(transactions.body) as i;
+/// ```
+#[pg_extern(immutable)]
+fn tx_inputs_table(
+    tx_cbor: &[u8],
+    consumed: default!(bool, true),
+) -> Option<TableIterator<'static, (name!(tx_hash, Vec<u8>), name!(output_index, i32))>> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let inputs = if consumed { tx.consumes() } else { tx.inputs() };
+
+    let inputs_data = inputs
+        .iter()
+        .map(|i| Ok((i.hash().to_vec(), i32::try_from(i.index())?)))
+        .collect::<Result<Vec<_>, std::num::TryFromIntError>>();
+
+    match inputs_data {
+        Ok(x) => Some(TableIterator::new(x)),
+        Err(err) => on_decode_error(err, TableIterator::new(std::iter::empty())),
+    }
+}
+
+fn has_input(inputs: &[pallas::ledger::traverse::MultiEraInput], hash: &[u8], index: i32) -> bool {
+    let index = match u64::try_from(index) {
+        Ok(x) => x,
+        Err(_) => return false,
+    };
+
+    inputs
+        .iter()
+        .any(|i| i.hash().as_ref() == hash && i.index() == index)
+}
+
+/// Returns whether the given transaction spends an output. With `consumed`
+/// (the default) phase-2 invalid transactions spend their collateral inputs
+/// rather than the inputs in the body, as in `tx_inputs_table`.
+#[pg_extern(immutable)]
+fn tx_has_input(
+    tx_cbor: &[u8],
+    hash: &[u8],
+    index: i32,
+    consumed: default!(bool, true),
+) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    let inputs = if consumed { tx.consumes() } else { tx.inputs() };
+
+    Some(has_input(&inputs, hash, index))
+}
+
+#[pg_extern(immutable)]
+fn tx_has_reference_input(tx_cbor: &[u8], hash: &[u8], index: i32) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(has_input(&tx.reference_inputs(), hash, index))
+}
+
+/// Returns the outputs of the given transaction data.
+///
+/// With `produced` (the default) the outputs are the ones the ledger actually
+/// creates: the transaction outputs when the transaction is valid, or just the
+/// collateral return (indexed after the last output) when it failed phase-2
+/// validation. Otherwise the outputs are returned as they appear in the body.
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_outputs(
+    tx_cbor: &[u8],
+    produced: default!(bool, true),
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(output_index, i32),
+            name!(address, Option<String>),
+            name!(lovelace, pgrx::AnyNumeric),
+            name!(assets, pgrx::Json),
+            name!(datum, pgrx::Json),
+            name!(cbor, Vec<u8>),
+            name!(asset_fingerprints, pgrx::Json),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let outputs = if produced {
+        tx.produces()
+    } else {
+        tx.outputs().into_iter().enumerate().collect()
+    };
+
+    let outputs_data = outputs
+        .iter()
+        .map(|(i, o)| output_row(*i, o))
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(outputs_data))
+}
+
+/// Builds a `tx_outputs` row out of an output and its index in the transaction.
+/// `asset_fingerprints` maps the CIP-14 fingerprint of each asset to its amount.
+#[allow(clippy::type_complexity)]
+fn output_row(
+    index: usize,
+    output: &MultiEraOutput,
+) -> (
+    i32,
+    Option<String>,
+    pgrx::AnyNumeric,
+    pgrx::Json,
+    pgrx::Json,
+    Vec<u8>,
+    pgrx::Json,
+) {
+    (
+        index as i32,
+        output_to_address_string(output),
+        AnyNumeric::from(output.value().coin()),
+        pgrx::Json(
+            serde_json::to_value(
+                output
+                    .value()
+                    .assets()
+                    .iter()
+                    .map(|asset| {
+                        let policy_id = hex::encode(asset.policy().as_ref());
+                        let assets: HashMap<String, i128> = asset
+                            .assets()
+                            .iter()
+                            .map(|a| (hex::encode(a.name()), a.any_coin()))
+                            .collect();
+
+                        (policy_id, assets)
+                    })
+                    .collect::<HashMap<_, _>>(),
+            )
+            .unwrap(),
+        ),
+        match output.datum() {
+            Some(d) => match d {
+                pallas::ledger::primitives::conway::PseudoDatumOption::Hash(h) => {
+                    pgrx::Json(serde_json::json!(h))
+                }
+                pallas::ledger::primitives::conway::PseudoDatumOption::Data(d) => {
+                    pgrx::Json(d.unwrap().deref().to_json())
+                }
+            },
+            None => pgrx::Json(serde_json::json!(null)),
+        },
+        output.encode(),
+        pgrx::Json(
+            serde_json::to_value(
+                output
+                    .value()
+                    .assets()
+                    .iter()
+                    .flat_map(|asset| {
+                        asset
+                            .assets()
+                            .iter()
+                            .map(|a| {
+                                let fingerprint =
+                                    asset_fingerprint_hash(asset.policy().as_ref(), a.name());
+                                (encode_asset_fingerprint(&fingerprint), a.any_coin())
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<HashMap<_, _>>(),
+            )
+            .unwrap(),
+        ),
+    )
+}
+
+#[pg_extern(immutable)]
+fn tx_outputs_json(tx_cbor: &[u8]) -> Option<pgrx::JsonB> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, pgrx::JsonB(serde_json::json!([]))),
+    };
+    let tx = tx.get();
+
+    let outputs_data: Vec<serde_json::Value> = tx
+        .produces()
+        .iter()
+        .map(|(i, o)| {
+            serde_json::json!({
+                "output_index": *i as i32,
+                "address": output_to_address_string(o),
+                "lovelace": o.value().coin().to_string(),
+                "assets": o.value().assets()
+                    .iter()
+                    .map(|asset| {
+                        let policy_id = hex::encode(asset.policy().as_ref());
+                        let assets: HashMap<String, String> = asset
+                            .assets()
+                            .iter()
+                            .map(|a| (hex::encode(a.name()), a.any_coin().to_string()))
+                            .collect();
+
+                        (policy_id, assets)
+                    })
+                    .collect::<HashMap<_, _>>(),
+                "datum": match o.datum() {
+                    Some(d) => match d {
+                        pallas::ledger::primitives::conway::PseudoDatumOption::Hash(h) =>
+                            serde_json::json!(h),
+                        pallas::ledger::primitives::conway::PseudoDatumOption::Data(d) =>
+                            d.unwrap().deref().to_json(),
+                    },
+                    None => serde_json::json!(null),
+                },
+            })
+        })
+        .collect();
+
+    Some(pgrx::JsonB(serde_json::json!(outputs_data)))
+}
+
+#[pg_extern(immutable)]
+fn tx_is_valid(tx_cbor: &[u8]) -> Option<bool> {
+    match decode_tx(tx_cbor) {
+        Ok(x) => Some(x.get().is_valid()),
+        Err(err) => on_decode_error(err, false),
+    }
+}
+
+/// Returns the first slot in which the given transaction is valid.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// The validity start, or NULL if it has no lower bound (like the unbounded
+/// side of `tx_validity_range`). -1 is only ever the decode error sentinel.
+///
+/// # Example
+///
+/// ```
+/// select tx_validity_start(body) from transactions;
+/// ```
+#[pg_extern(immutable)]
+fn tx_validity_start(tx_cbor: &[u8]) -> Option<i64> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, -1),
+    };
+    let tx = tx.get();
+
+    tx.validity_start().map(|x| x as i64)
+}
+
+/// Returns the TTL of the given transaction as written, or NULL if it is
+/// valid indefinitely. The TTL is the last valid slot in Shelley and the first
+/// invalid one from Allegra onwards; see `tx_validity_range`.
+#[pg_extern(immutable)]
+fn tx_validity_end(tx_cbor: &[u8]) -> Option<i64> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, -1),
+    };
+    let tx = tx.get();
+
+    tx.ttl().map(|x| x as i64)
+}
+
+/// Resolves the era whose validity rules apply to a transaction: the given
+/// one, or else the era it decoded as. Transaction bytes alone can't tell
+/// Shelley apart from the later eras that share its layout, which all decode
+/// as Alonzo.
+fn validity_era(
+    tx: &MultiEraTx,
+    era: Option<i32>,
+) -> Result<pallas::ledger::traverse::Era, String> {
+    match era {
+        Some(x) => u16::try_from(x)
+            .ok()
+            .and_then(|x| pallas::ledger::traverse::Era::try_from(x).ok())
+            .ok_or_else(|| format!("invalid era: {}", x)),
+        None => Ok(tx.era()),
+    }
+}
+
+/// Returns the first slot in which a transaction is no longer valid. The
+/// Shelley ledger accepts a transaction in its TTL slot, while the Allegra
+/// `invalid_hereafter` that replaced it is exclusive.
+fn validity_end_exclusive(tx: &MultiEraTx, era: pallas::ledger::traverse::Era) -> Option<i64> {
+    let ttl = tx.ttl()? as i64;
+
+    if era == pallas::ledger::traverse::Era::Shelley {
+        Some(ttl.saturating_add(1))
+    } else {
+        Some(ttl)
+    }
+}
+
+/// Returns the slots in which the given transaction is valid.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+/// * `era` - The era of the transaction, needed to apply the Shelley rule
+///   (the TTL slot is included) since Shelley transactions decode as Alonzo.
+///
+/// # Returns
+///
+/// An `int8range` including the validity start and excluding the first
+/// invalid slot, with missing bounds left unbounded.
+///
+/// # Example
+///
+/// ```
+/// create index on transactions using gist (tx_validity_range(body));
+/// select tx_hash(body) from transactions where tx_validity_range(body) @> 120000000::int8;
+/// ```
+#[pg_extern(immutable)]
+fn tx_validity_range(
+    tx_cbor: &[u8],
+    era: default!(Option<i32>, "NULL"),
+) -> Option<pgrx::Range<i64>> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, pgrx::Range::empty()),
+    };
+    let tx = tx.get();
+
+    let era = match validity_era(tx, era) {
+        Ok(x) => x,
+        Err(err) => return on_invalid_argument(err, pgrx::Range::empty()),
+    };
+
+    let start = match tx.validity_start() {
+        Some(x) => RangeBound::Inclusive(x as i64),
+        None => RangeBound::Infinite,
+    };
+    let end = match validity_end_exclusive(tx, era) {
+        Some(x) => RangeBound::Exclusive(x),
+        None => RangeBound::Infinite,
+    };
+
+    Some(pgrx::Range::new(start, end))
+}
+
+#[pg_extern(immutable)]
+fn tx_is_valid_at_slot(
+    tx_cbor: &[u8],
+    slot: i64,
+    era: default!(Option<i32>, "NULL"),
+) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    let era = match validity_era(tx, era) {
+        Ok(x) => x,
+        Err(err) => return on_invalid_argument(err, false),
+    };
+
+    let after_start = tx.validity_start().is_none_or(|x| slot >= x as i64);
+    let before_end = validity_end_exclusive(tx, era).is_none_or(|x| slot < x);
+
+    Some(after_start && before_end)
+}
+
+#[pg_extern(immutable)]
+fn tx_addresses(tx_cbor: &[u8]) -> Option<Vec<Option<String>>> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+    let tx = tx.get();
+
+    let outputs_data = tx
+        .outputs()
+        .iter()
+        .map(|o| output_to_address_string(o))
+        .collect::<Vec<_>>();
+
+    Some(outputs_data)
+}
+
+#[pg_extern(immutable)]
+fn tx_plutus_data(tx_cbor: &[u8]) -> Option<pgrx::JsonB> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, pgrx::JsonB(serde_json::json!([]))),
+    };
+    let tx = tx.get();
+
+    let plutus_data: Vec<serde_json::Value> =
+        tx.plutus_data().iter().map(|x| x.to_json()).collect();
+
+    Some(pgrx::JsonB(serde_json::json!(plutus_data)))
+}
+
+fn redeemer_tag_name(tag: conway::RedeemerTag) -> &'static str {
+    match tag {
+        conway::RedeemerTag::Spend => "spend",
+        conway::RedeemerTag::Mint => "mint",
+        conway::RedeemerTag::Cert => "cert",
+        conway::RedeemerTag::Reward => "reward",
+        conway::RedeemerTag::Vote => "vote",
+        conway::RedeemerTag::Propose => "propose",
+    }
+}
+
+/// Returns the data of each redeemer exactly as it appears in the witness
+/// set, with the purpose and index of the redeemer. pallas doesn't keep these
+/// bytes and re-encoding what it decoded may not give them back (nor their
+/// hash) when the original encoding isn't canonical.
+fn raw_redeemer_data<'a>(tx: &'a MultiEraTx) -> Vec<(conway::RedeemerTag, u32, &'a [u8])> {
+    use pallas::codec::minicbor::data::Type;
+    use pallas::codec::minicbor::Decoder;
+
+    let witnesses = if let Some(x) = tx.as_conway() {
+        x.transaction_witness_set.raw_cbor()
+    } else if let Some(x) = tx.as_babbage() {
+        x.transaction_witness_set.raw_cbor()
+    } else if let Some(x) = tx.as_alonzo() {
+        x.transaction_witness_set.raw_cbor()
+    } else {
+        return vec![];
+    };
+
+    let scan = || -> Option<Vec<_>> {
+        let mut d = Decoder::new(witnesses);
+
+        for _ in 0..d.map().ok()?? {
+            if d.u64().ok()? != 5 {
+                d.skip().ok()?;
+                continue;
+            }
+
+            // [* [tag, index, data, ex_units]] or, from conway,
+            // {* [tag, index] => [data, ex_units]}
+            let is_map = matches!(d.datatype().ok()?, Type::Map | Type::MapIndef);
+            let len = if is_map {
+                d.map().ok()?
+            } else {
+                d.array().ok()?
+            };
+
+            let mut redeemers = vec![];
+            while len.is_none_or(|x| redeemers.len() < x as usize) {
+                if len.is_none() && d.datatype().ok()? == Type::Break {
+                    break;
+                }
+
+                d.array().ok()?;
+                let tag = d.decode().ok()?;
+                let index = d.u32().ok()?;
+                if is_map {
+                    d.array().ok()?;
+                }
+
+                let start = d.position();
+                d.skip().ok()?;
+                redeemers.push((tag, index, &witnesses[start..d.position()]));
+
+                d.skip().ok()?;
+            }
+
+            return Some(redeemers);
+        }
+
+        Some(vec![])
+    };
+
+    scan().unwrap_or_default()
+}
+
+/// Returns the redeemers of the given transaction data.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// A row per redeemer with its purpose (`spend`, `mint`, `cert`, `reward`,
+/// `vote` or `propose`), the index of the item it is for, its data as JSON
+/// and as the CBOR found in the witness set and the memory and CPU steps
+/// budgeted for it.
+///
+/// # Example
+///
+/// ```
+/// select r.tag, sum(r.steps) from transactions, lateral tx_redeemers(transactions.body) as r group by r.tag;
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_redeemers(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(tag, String),
+            name!(index, i32),
+            name!(data, pgrx::JsonB),
+            name!(data_cbor, Vec<u8>),
+            name!(mem, i64),
+            name!(steps, i64),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let raw_data = raw_redeemer_data(tx);
+
+    let redeemers_data = tx
+        .redeemers()
+        .iter()
+        .map(|r| {
+            let data_cbor = raw_data
+                .iter()
+                .find(|(tag, index, _)| *tag == r.tag() && *index == r.index())
+                .map(|(.., x)| x.to_vec())
+                .unwrap_or_else(|| pallas::codec::minicbor::to_vec(r.data()).unwrap());
+
+            (
+                redeemer_tag_name(r.tag()).to_string(),
+                r.index() as i32,
+                pgrx::JsonB(r.data().to_json()),
+                data_cbor,
+                r.ex_units().mem as i64,
+                r.ex_units().steps as i64,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(redeemers_data))
+}
+
+#[pg_extern(immutable)]
+fn tx_total_ex_units_mem(tx_cbor: &[u8]) -> Option<i64> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, 0),
+    };
+    let tx = tx.get();
+
+    Some(tx.redeemers().iter().map(|r| r.ex_units().mem as i64).sum())
+}
+
+#[pg_extern(immutable)]
+fn tx_total_ex_units_steps(tx_cbor: &[u8]) -> Option<i64> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, 0),
+    };
+    let tx = tx.get();
+
+    Some(
+        tx.redeemers()
+            .iter()
+            .map(|r| r.ex_units().steps as i64)
+            .sum(),
+    )
+}
+
+/// Returns the vkey witnesses of the given transaction data.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// A row per witness with the public key, its blake2b-224 hash (the key hash
+/// used in addresses and required signers) and the signature.
+///
+/// # Example
+///
+/// ```
+/// select w.key_hash from transactions, lateral tx_vkey_witnesses(transactions.body) as w;
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_vkey_witnesses(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(public_key, Vec<u8>),
+            name!(key_hash, Vec<u8>),
+            name!(signature, Vec<u8>),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let witnesses_data = tx
+        .vkey_witnesses()
+        .iter()
+        .map(|w| {
+            (
+                w.vkey.to_vec(),
+                Hasher::<224>::hash(&w.vkey).to_vec(),
+                w.signature.to_vec(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(witnesses_data))
+}
+
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_bootstrap_witnesses(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(public_key, Vec<u8>),
+            name!(signature, Vec<u8>),
+            name!(chain_code, Vec<u8>),
+            name!(attributes, Vec<u8>),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let witnesses_data = tx
+        .bootstrap_witnesses()
+        .iter()
+        .map(|w| {
+            (
+                w.public_key.to_vec(),
+                w.signature.to_vec(),
+                w.chain_code.to_vec(),
+                w.attributes.to_vec(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(witnesses_data))
+}
+
+fn verify_ed25519(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    use pallas::crypto::key::ed25519::{PublicKey, Signature};
+
+    let public_key = match <[u8; PublicKey::SIZE]>::try_from(public_key) {
+        Ok(x) => PublicKey::from(x),
+        Err(_) => return false,
+    };
+    let signature = match <[u8; Signature::SIZE]>::try_from(signature) {
+        Ok(x) => Signature::from(x),
+        Err(_) => return false,
+    };
+
+    public_key.verify(message, &signature)
+}
+
+/// Verifies the signatures of the given transaction data against its body hash.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// A row per vkey and bootstrap witness with the kind of witness, its public
+/// key, the key hash (vkey witnesses only) and whether its signature is valid.
+/// Byron transactions carry their witnesses elsewhere and yield no rows.
+///
+/// # Example
+///
+/// ```
+/// select v.* from transactions, lateral tx_verify_signatures(transactions.body) as v where not v.valid;
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_verify_signatures(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(witness_type, String),
+            name!(public_key, Vec<u8>),
+            name!(key_hash, Option<Vec<u8>>),
+            name!(valid, bool),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let hash = tx.hash();
+
+    let vkey = tx.vkey_witnesses().iter().map(|w| {
+        (
+            "vkey".to_string(),
+            w.vkey.to_vec(),
+            Some(Hasher::<224>::hash(&w.vkey).to_vec()),
+            verify_ed25519(&w.vkey, hash.as_ref(), &w.signature),
+        )
+    });
+    let bootstrap = tx.bootstrap_witnesses().iter().map(|w| {
+        (
+            "bootstrap".to_string(),
+            w.public_key.to_vec(),
+            None,
+            verify_ed25519(&w.public_key, hash.as_ref(), &w.signature),
+        )
+    });
+
+    let signatures_data = vkey.chain(bootstrap).collect::<Vec<_>>();
+
+    Some(TableIterator::new(signatures_data))
+}
+
+/// Returns whether every vkey and bootstrap witness of the given transaction
+/// data has a valid signature, or NULL when there is none to check (unsigned
+/// and Byron transactions, as `tx_verify_signatures` returns no rows for them).
+#[pg_extern(immutable)]
+fn tx_has_valid_signatures(tx_cbor: &[u8]) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    let hash = tx.hash();
+
+    let vkey_witnesses = tx.vkey_witnesses();
+    let bootstrap_witnesses = tx.bootstrap_witnesses();
+
+    if vkey_witnesses.is_empty() && bootstrap_witnesses.is_empty() {
+        return None;
+    }
+
+    let vkey = vkey_witnesses
+        .iter()
+        .all(|w| verify_ed25519(&w.vkey, hash.as_ref(), &w.signature));
+    let bootstrap = bootstrap_witnesses
+        .iter()
+        .all(|w| verify_ed25519(&w.public_key, hash.as_ref(), &w.signature));
+
+    Some(vkey && bootstrap)
+}
+
+#[pg_extern(immutable)]
+fn tx_required_signers(tx_cbor: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+    let tx = tx.get();
+
+    Some(
+        tx.required_signers()
+            .collect::<Vec<_>>()
+            .iter()
+            .map(|x| x.to_vec())
+            .collect(),
+    )
+}
+
+#[pg_extern(immutable)]
+fn tx_has_required_signer(tx_cbor: &[u8], key_hash: &[u8]) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(
+        tx.required_signers()
+            .collect::<Vec<_>>()
+            .iter()
+            .any(|x| x.as_ref() == key_hash),
+    )
+}
+
+/// Returns the key hashes that sign the given transaction data: its required
+/// signers followed by the hashes of its vkey witnesses, without duplicates.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// An array of key hashes.
+///
+/// # Example
+///
+/// ```
+/// select tx_hash(body) from transactions where decode('key_hash_hex', 'hex') = any(tx_signer_key_hashes(body));
+/// ```
+#[pg_extern(immutable)]
+fn tx_signer_key_hashes(tx_cbor: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+    let tx = tx.get();
+
+    let required = tx
+        .required_signers()
+        .collect::<Vec<_>>()
+        .iter()
+        .map(|x| x.to_vec())
+        .collect::<Vec<_>>();
+    let witnesses = tx
+        .vkey_witnesses()
+        .iter()
+        .map(|w| Hasher::<224>::hash(&w.vkey).to_vec());
+
+    let mut key_hashes: Vec<Vec<u8>> = vec![];
+    for key_hash in required.into_iter().chain(witnesses) {
+        if !key_hashes.contains(&key_hash) {
+            key_hashes.push(key_hash);
+        }
+    }
+
+    Some(key_hashes)
+}
+
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_native_scripts(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(script_hash, Vec<u8>),
+            name!(script, pgrx::JsonB),
+            name!(cbor, Vec<u8>),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let scripts_data = tx
+        .native_scripts()
+        .iter()
+        .map(|s| {
+            (
+                s.original_hash().to_vec(),
+                pgrx::JsonB(serde_json::to_value(s.deref()).unwrap()),
+                s.raw_cbor().to_vec(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(scripts_data))
+}
+
+/// Returns the Plutus scripts in the witness set of the given transaction
+/// data.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// A row per script with its language (`plutus_v1`, `plutus_v2` or
+/// `plutus_v3`), its hash and the script bytes.
+///
+/// # Example
+///
+/// ```
+/// select s.language, s.script_hash from transactions, lateral tx_plutus_scripts(transactions.body) as s;
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_plutus_scripts(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(language, String),
+            name!(script_hash, Vec<u8>),
+            name!(script, Vec<u8>),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let v1 = tx.plutus_v1_scripts().iter().map(|s| {
+        (
+            "plutus_v1".to_string(),
+            s.compute_hash().to_vec(),
+            s.0.to_vec(),
+        )
+    });
+    let v2 = tx.plutus_v2_scripts().iter().map(|s| {
+        (
+            "plutus_v2".to_string(),
+            s.compute_hash().to_vec(),
+            s.0.to_vec(),
+        )
+    });
+    let v3 = tx.plutus_v3_scripts().iter().map(|s| {
+        (
+            "plutus_v3".to_string(),
+            s.compute_hash().to_vec(),
+            s.0.to_vec(),
+        )
+    });
+
+    let scripts_data = v1.chain(v2).chain(v3).collect::<Vec<_>>();
+
+    Some(TableIterator::new(scripts_data))
+}
+
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_witness_datums(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(datum_hash, Vec<u8>),
+            name!(datum, pgrx::JsonB),
+            name!(cbor, Vec<u8>),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let datums_data = tx
+        .plutus_data()
+        .iter()
+        .map(|d| {
+            (
+                d.original_hash().to_vec(),
+                pgrx::JsonB(d.to_json()),
+                d.raw_cbor().to_vec(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(datums_data))
+}
+
+/// Returns the lovelace sent to the outputs of the given transaction data.
+///
+/// Like `tx_outputs`, it sums what the ledger produces by default, i.e. only
+/// the collateral return of a phase-2 invalid transaction; with `produced`
+/// unset the outputs are taken as they appear in the body.
+#[pg_extern(immutable)]
+fn tx_lovelace(tx_cbor: &[u8], produced: default!(bool, true)) -> Option<pgrx::AnyNumeric> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+    let tx = tx.get();
+
+    let lovelace = if produced {
+        tx.produces()
+            .iter()
+            .map(|(_, o)| o.value().coin())
+            .sum::<u64>()
+    } else {
+        tx.outputs().iter().map(|o| o.value().coin()).sum::<u64>()
+    };
+
+    Some(AnyNumeric::from(lovelace))
+}
+
+#[pg_extern(immutable)]
+fn tx_collateral_inputs(tx_cbor: &[u8]) -> Option<Vec<Option<String>>> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+    let tx = tx.get();
+
+    Some(
+        tx.collateral()
+            .iter()
+            .map(|i| Some(format!("{}#{}", i.hash(), i.index())))
+            .collect::<Vec<Option<String>>>(),
+    )
+}
+
+/// Returns the collateral return of the given transaction data as a
+/// `tx_outputs` row, indexed after the last output like the ledger does.
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_collateral_return(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(output_index, i32),
+            name!(address, Option<String>),
+            name!(lovelace, pgrx::AnyNumeric),
+            name!(assets, pgrx::Json),
+            name!(datum, pgrx::Json),
+            name!(cbor, Vec<u8>),
+            name!(asset_fingerprints, pgrx::Json),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let outputs_data = tx
+        .collateral_return()
+        .iter()
+        .map(|o| output_row(tx.outputs().len(), o))
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(outputs_data))
+}
+
+#[pg_extern(immutable)]
+fn tx_total_collateral(tx_cbor: &[u8]) -> Option<pgrx::AnyNumeric> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+    let tx = tx.get();
+
+    tx.total_collateral().map(AnyNumeric::from)
+}
+
+#[pg_extern(immutable)]
+fn tx_fee(tx_cbor: &[u8]) -> Option<pgrx::AnyNumeric> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+    let tx = tx.get();
+    let fee = match tx.fee() {
+        Some(f) => f,
+        None => return Some(AnyNumeric::from(0)),
+    };
+    Some(AnyNumeric::from(fee))
+}
+
+#[pg_extern(immutable)]
+fn tx_mint(tx_cbor: &[u8]) -> Option<pgrx::JsonB> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, None).flatten(),
+    };
+    let tx = tx.get();
+
+    let mints = tx.mints();
+
+    let mint_data: HashMap<String, HashMap<String, String>> = mints
+        .iter()
+        .map(|m| {
+            let policy_id = hex::encode(m.policy().as_ref());
+            let assets: HashMap<String, String> = m
+                .assets()
+                .iter()
+                .map(|a| (hex::encode(a.name()), a.any_coin().to_string()))
+                .collect();
+
+            (policy_id, assets)
+        })
+        .collect();
+
+    if mint_data.is_empty() {
+        return None;
+    }
+
+    Some(pgrx::JsonB(serde_json::json!(mint_data)))
+}
+
+/// Returns the value of an output of a transaction.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The CBOR of the transaction.
+/// * `output_index` - The index of the output, as listed by `tx_outputs`
+///   (the collateral return of an invalid transaction included).
+///
+/// # Returns
+///
+/// The `cardano_value` of the output, NULL if there's no such output.
+///
+/// # Example
+///
+/// ```
+/// select tx_output_value(cbor, 0) from txs;
+/// ```
+#[pg_extern(immutable)]
+fn tx_output_value(tx_cbor: &[u8], output_index: i32) -> Option<cardano_value> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, None).flatten(),
+    };
+    let tx = tx.get();
+
+    tx.produces()
+        .iter()
+        .find(|(i, _)| *i as i64 == output_index as i64)
+        .map(|(_, output)| ValueAmounts::from_output(output).into())
+}
+
+/// Returns the assets minted (positive quantities) and burned (negative
+/// quantities) by a transaction as a `cardano_value` without lovelace.
+#[pg_extern(immutable)]
+fn tx_mint_value(tx_cbor: &[u8]) -> Option<cardano_value> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, ValueAmounts::default().into()),
+    };
+
+    Some(ValueAmounts::from_mints(tx.get()).into())
+}
+
+#[pg_extern(immutable)]
+fn tx_subject_amount_output(tx_cbor: &[u8], subject: &[u8]) -> Option<pgrx::AnyNumeric> {
+    match asset_subject::from_bytes(subject) {
+        Ok(x) => tx_asset_subject_amount_output(tx_cbor, x),
+        Err(err) => on_invalid_argument(err, AnyNumeric::from(0)),
+    }
+}
+
+/// Like `tx_subject_amount_output`, but takes an `asset_subject`. It has its
+/// own name so that untyped literals passed to either function resolve.
+#[pg_extern(immutable)]
+fn tx_asset_subject_amount_output(
+    tx_cbor: &[u8],
+    subject: asset_subject,
+) -> Option<pgrx::AnyNumeric> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+    let tx = tx.get();
+
+    let amount = tx
+        .produces()
+        .iter()
+        .map(|(_, o)| subject.output_amount(o))
+        .sum::<i128>();
+
+    Some(AnyNumeric::from(amount))
+}
+
+#[pg_extern(immutable)]
+fn tx_subject_amount_mint(tx_cbor: &[u8], subject: &[u8]) -> Option<pgrx::AnyNumeric> {
+    match asset_subject::from_bytes(subject) {
+        Ok(x) => tx_asset_subject_amount_mint(tx_cbor, x),
+        Err(err) => on_invalid_argument(err, AnyNumeric::from(0)),
+    }
+}
+
+/// Like `tx_subject_amount_mint`, but takes an `asset_subject`.
+#[pg_extern(immutable)]
+fn tx_asset_subject_amount_mint(
+    tx_cbor: &[u8],
+    subject: asset_subject,
+) -> Option<pgrx::AnyNumeric> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+
+    Some(AnyNumeric::from(subject.mint_amount(tx.get()).unwrap_or(0)))
+}
+
+#[pg_extern(immutable)]
+fn tx_withdrawals(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(stake_address, Vec<u8>),
+            name!(amount, pgrx::AnyNumeric),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let withdrawals_data = match tx.withdrawals() {
+        MultiEraWithdrawals::AlonzoCompatible(w) => w
+            .iter()
+            .map(|(k, v)| (k.to_vec(), AnyNumeric::from(*v)))
+            .collect::<Vec<_>>(),
+        _ => vec![],
+    };
+    Some(TableIterator::new(withdrawals_data))
+}
+
+#[pg_extern(immutable)]
+fn tx_withdrawals_json(tx_cbor: &[u8]) -> Option<pgrx::JsonB> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, pgrx::JsonB(serde_json::json!({}))),
+    };
+    let tx = tx.get();
+
+    let withdrawals_data: HashMap<String, String> = match tx.withdrawals() {
+        MultiEraWithdrawals::AlonzoCompatible(w) => w
+            .iter()
+            .map(|(k, v)| (hex::encode(k.to_vec()), v.to_string()))
+            .collect(),
+        _ => HashMap::new(),
+    };
+
+    Some(pgrx::JsonB(serde_json::json!(withdrawals_data)))
+}
+
+/// Kinds reported by `tx_certificates`, named after the certificates in the
+/// ledger CDDL.
+const CERTIFICATE_KINDS: &[&str] = &[
+    "stake_registration",
+    "stake_deregistration",
+    "stake_delegation",
+    "pool_registration",
+    "pool_retirement",
+    "genesis_key_delegation",
+    "move_instantaneous_rewards",
+    "reg",
+    "unreg",
+    "vote_deleg",
+    "stake_vote_deleg",
+    "stake_reg_deleg",
+    "vote_reg_deleg",
+    "stake_vote_reg_deleg",
+    "auth_committee_hot",
+    "resign_committee_cold",
+    "reg_drep",
+    "unreg_drep",
+    "update_drep",
+];
+
+/// The fields of a certificate shared by `tx_certificates` and
+/// `tx_certificates_json`. `credential` is the stake, DRep, committee cold or
+/// genesis key hash the certificate is about.
+struct CertificateInfo {
+    kind: &'static str,
+    credential: Option<Vec<u8>>,
+    credential_is_script: Option<bool>,
+    pool_id: Option<Vec<u8>>,
+    deposit: Option<u64>,
+}
+
+impl CertificateInfo {
+    fn new(kind: &'static str) -> Self {
+        CertificateInfo {
+            kind,
+            credential: None,
+            credential_is_script: None,
+            pool_id: None,
+            deposit: None,
+        }
+    }
+
+    fn credential(mut self, credential: &StakeCredential) -> Self {
+        let (hash, is_script) = match credential {
+            StakeCredential::AddrKeyhash(x) => (x.to_vec(), false),
+            StakeCredential::ScriptHash(x) => (x.to_vec(), true),
+        };
+        self.credential = Some(hash);
+        self.credential_is_script = Some(is_script);
+        self
+    }
+
+    fn pool_id(mut self, pool_id: &pallas::crypto::hash::Hash<28>) -> Self {
+        self.pool_id = Some(pool_id.to_vec());
+        self
+    }
+
+    fn deposit(mut self, deposit: u64) -> Self {
+        self.deposit = Some(deposit);
+        self
+    }
+}
+
+fn certificate_info(cert: &MultiEraCert) -> Option<CertificateInfo> {
+    if let Some(cert) = cert.as_alonzo() {
+        return Some(match cert {
+            alonzo::Certificate::StakeRegistration(c) => {
+                CertificateInfo::new("stake_registration").credential(c)
+            }
+            alonzo::Certificate::StakeDeregistration(c) => {
+                CertificateInfo::new("stake_deregistration").credential(c)
+            }
+            alonzo::Certificate::StakeDelegation(c, p) => CertificateInfo::new("stake_delegation")
+                .credential(c)
+                .pool_id(p),
+            alonzo::Certificate::PoolRegistration { operator, .. } => {
+                CertificateInfo::new("pool_registration").pool_id(operator)
+            }
+            alonzo::Certificate::PoolRetirement(p, _) => {
+                CertificateInfo::new("pool_retirement").pool_id(p)
+            }
+            alonzo::Certificate::GenesisKeyDelegation(g, _, _) => CertificateInfo {
+                credential: Some(g.to_vec()),
+                credential_is_script: Some(false),
+                ..CertificateInfo::new("genesis_key_delegation")
+            },
+            alonzo::Certificate::MoveInstantaneousRewardsCert(_) => {
+                CertificateInfo::new("move_instantaneous_rewards")
+            }
+        });
+    }
+
+    let cert = cert.as_conway()?;
+
+    Some(match cert {
+        conway::Certificate::StakeRegistration(c) => {
+            CertificateInfo::new("stake_registration").credential(c)
+        }
+        conway::Certificate::StakeDeregistration(c) => {
+            CertificateInfo::new("stake_deregistration").credential(c)
+        }
+        conway::Certificate::StakeDelegation(c, p) => CertificateInfo::new("stake_delegation")
+            .credential(c)
+            .pool_id(p),
+        conway::Certificate::PoolRegistration { operator, .. } => {
+            CertificateInfo::new("pool_registration").pool_id(operator)
+        }
+        conway::Certificate::PoolRetirement(p, _) => {
+            CertificateInfo::new("pool_retirement").pool_id(p)
+        }
+        conway::Certificate::Reg(c, d) => CertificateInfo::new("reg").credential(c).deposit(*d),
+        conway::Certificate::UnReg(c, d) => CertificateInfo::new("unreg").credential(c).deposit(*d),
+        conway::Certificate::VoteDeleg(c, _) => CertificateInfo::new("vote_deleg").credential(c),
+        conway::Certificate::StakeVoteDeleg(c, p, _) => CertificateInfo::new("stake_vote_deleg")
+            .credential(c)
+            .pool_id(p),
+        conway::Certificate::StakeRegDeleg(c, p, d) => CertificateInfo::new("stake_reg_deleg")
+            .credential(c)
+            .pool_id(p)
+            .deposit(*d),
+        conway::Certificate::VoteRegDeleg(c, _, d) => CertificateInfo::new("vote_reg_deleg")
+            .credential(c)
+            .deposit(*d),
+        conway::Certificate::StakeVoteRegDeleg(c, p, _, d) => {
+            CertificateInfo::new("stake_vote_reg_deleg")
+                .credential(c)
+                .pool_id(p)
+                .deposit(*d)
+        }
+        conway::Certificate::AuthCommitteeHot(c, _) => {
+            CertificateInfo::new("auth_committee_hot").credential(c)
+        }
+        conway::Certificate::ResignCommitteeCold(c, _) => {
+            CertificateInfo::new("resign_committee_cold").credential(c)
+        }
+        conway::Certificate::RegDRepCert(c, d, _) => {
+            CertificateInfo::new("reg_drep").credential(c).deposit(*d)
+        }
+        conway::Certificate::UnRegDRepCert(c, d) => {
+            CertificateInfo::new("unreg_drep").credential(c).deposit(*d)
+        }
+        conway::Certificate::UpdateDRepCert(c, _) => {
+            CertificateInfo::new("update_drep").credential(c)
+        }
+    })
+}
+
+/// Returns the certificates of the given transaction data.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// A row per certificate with its position in the transaction (certificates
+/// of unknown kinds are skipped but still counted), its kind (see
+/// `CERTIFICATE_KINDS`), the credential it is about and whether that is a
+/// script hash, the pool id and the deposit (or refund) when the certificate
+/// carries them.
+///
+/// # Example
+///
+/// ```
+/// select c.* from txs, lateral tx_certificates(txs.cbor) as c where c.kind = 'pool_retirement';
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_certificates(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(index, i32),
+            name!(kind, String),
+            name!(credential, Option<Vec<u8>>),
+            name!(credential_is_script, Option<bool>),
+            name!(pool_id, Option<Vec<u8>>),
+            name!(deposit, Option<pgrx::AnyNumeric>),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let certificates_data = tx
+        .certs()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| Some((i, certificate_info(c)?)))
+        .map(|(i, c)| {
+            (
+                i as i32,
+                c.kind.to_string(),
+                c.credential,
+                c.credential_is_script,
+                c.pool_id,
+                c.deposit.map(AnyNumeric::from),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(certificates_data))
+}
+
+#[pg_extern(immutable)]
+fn tx_certificates_json(tx_cbor: &[u8]) -> Option<pgrx::JsonB> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, pgrx::JsonB(serde_json::json!([]))),
+    };
+    let tx = tx.get();
+
+    let certificates_data: Vec<serde_json::Value> = tx
+        .certs()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| Some((i, certificate_info(c)?)))
+        .map(|(i, c)| {
+            serde_json::json!({
+                "index": i,
+                "kind": c.kind,
+                "credential": c.credential.map(hex::encode),
+                "credential_is_script": c.credential_is_script,
+                "pool_id": c.pool_id.map(hex::encode),
+                "deposit": c.deposit.map(|x| x.to_string()),
+            })
+        })
+        .collect();
+
+    Some(pgrx::JsonB(serde_json::json!(certificates_data)))
+}
+
+#[pg_extern(immutable)]
+fn tx_has_certificate_kind(tx_cbor: &[u8], kind: &str) -> Option<bool> {
+    if !CERTIFICATE_KINDS.contains(&kind) {
+        return on_invalid_argument(format!("unknown certificate kind: {}", kind), false);
+    }
+
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(
+        tx.certs()
+            .iter()
+            .filter_map(certificate_info)
+            .any(|c| c.kind == kind),
+    )
+}
+
+fn voter_info(voter: &conway::Voter) -> (&'static str, Vec<u8>) {
+    match voter {
+        conway::Voter::ConstitutionalCommitteeKey(x) => ("cc_hot_key", x.to_vec()),
+        conway::Voter::ConstitutionalCommitteeScript(x) => ("cc_hot_script", x.to_vec()),
+        conway::Voter::DRepKey(x) => ("drep_key", x.to_vec()),
+        conway::Voter::DRepScript(x) => ("drep_script", x.to_vec()),
+        conway::Voter::StakePoolKey(x) => ("spo", x.to_vec()),
+    }
+}
+
+fn anchor_parts(
+    anchor: &pallas::codec::utils::Nullable<conway::Anchor>,
+) -> (Option<String>, Option<Vec<u8>>) {
+    match anchor {
+        pallas::codec::utils::Nullable::Some(x) => {
+            (Some(x.url.clone()), Some(x.content_hash.to_vec()))
+        }
+        _ => (None, None),
+    }
+}
+
+/// Returns the votes cast by the given transaction data.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// A row per vote with the voter type (`cc_hot_key`, `cc_hot_script`,
+/// `drep_key`, `drep_script` or `spo`), the voter credential, the governance
+/// action id (transaction hash and index), the vote (`yes`, `no` or `abstain`)
+/// and the anchor url and hash, if any. Pre-Conway transactions yield no rows.
+///
+/// # Example
+///
+/// ```
+/// select v.vote, count(*) from transactions, lateral tx_votes(transactions.body) as v group by v.vote;
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_votes(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(voter_type, String),
+            name!(voter, Vec<u8>),
+            name!(gov_action_tx_hash, Vec<u8>),
+            name!(gov_action_index, i32),
+            name!(vote, String),
+            name!(anchor_url, Option<String>),
+            name!(anchor_hash, Option<Vec<u8>>),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let voting_procedures = tx
+        .as_conway()
+        .and_then(|x| x.transaction_body.voting_procedures.as_ref());
+
+    let votes_data = voting_procedures
+        .iter()
+        .flat_map(|x| x.iter())
+        .flat_map(|(voter, votes)| {
+            let (voter_type, voter) = voter_info(voter);
+
+            votes.iter().map(move |(action, procedure)| {
+                let (anchor_url, anchor_hash) = anchor_parts(&procedure.anchor);
+                let vote = match procedure.vote {
+                    conway::Vote::Yes => "yes",
+                    conway::Vote::No => "no",
+                    conway::Vote::Abstain => "abstain",
+                };
+
+                (
+                    voter_type.to_string(),
+                    voter.clone(),
+                    action.transaction_id.to_vec(),
+                    action.action_index as i32,
+                    vote.to_string(),
+                    anchor_url,
+                    anchor_hash,
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(votes_data))
+}
+
+/// Renders a governance action id as a CIP-129 `gov_action1...` identifier:
+/// the 32 bytes transaction hash followed by the action index as a single
+/// byte, which is why indexes above 255 have no such identifier.
+fn encode_gov_action_id(tx_hash: &[u8], index: u8) -> String {
+    let mut bytes = tx_hash.to_vec();
+    bytes.push(index);
+
+    bech32::encode("gov_action", bytes.to_base32(), bech32::Variant::Bech32).unwrap()
+}
+
+fn nullable_gov_action_id_to_json(
+    id: &pallas::codec::utils::Nullable<conway::GovActionId>,
+) -> serde_json::Value {
+    match id {
+        pallas::codec::utils::Nullable::Some(x) => match u8::try_from(x.action_index) {
+            Ok(index) => serde_json::json!(encode_gov_action_id(x.transaction_id.as_ref(), index)),
+            // beyond what a CIP-129 identifier can hold
+            Err(_) => serde_json::json!({
+                "transaction_id": hex::encode(x.transaction_id),
+                "action_index": x.action_index,
+            }),
+        },
+        _ => serde_json::Value::Null,
+    }
+}
+
+fn nullable_hash_to_json<const N: usize>(
+    hash: &pallas::codec::utils::Nullable<pallas::crypto::hash::Hash<N>>,
+) -> serde_json::Value {
+    match hash {
+        pallas::codec::utils::Nullable::Some(x) => serde_json::json!(hex::encode(x)),
+        _ => serde_json::Value::Null,
+    }
+}
+
+/// Renders a credential the way the ledger does when it is used as a JSON key.
+fn credential_to_json_key(credential: &StakeCredential) -> String {
+    match credential {
+        StakeCredential::AddrKeyhash(x) => format!("keyHash-{}", hex::encode(x)),
+        StakeCredential::ScriptHash(x) => format!("scriptHash-{}", hex::encode(x)),
+    }
+}
+
+/// Splits a governance action into its type and a JSON description of its
+/// contents, with previous actions rendered as `gov_action1...` ids, hashes
+/// in hex and lovelace amounts as strings.
+fn gov_action_to_json(action: &conway::GovAction) -> (&'static str, serde_json::Value) {
+    match action {
+        conway::GovAction::ParameterChange(prev, params, policy) => {
+            // only the parameters being updated are present in the proposal
+            let params = match serde_json::to_value(params.deref()).unwrap_or_default() {
+                serde_json::Value::Object(x) => x
+                    .into_iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .collect::<serde_json::Map<_, _>>(),
+                _ => serde_json::Map::new(),
+            };
+
+            (
+                "parameter_change",
+                serde_json::json!({
+                    "prev_action": nullable_gov_action_id_to_json(prev),
+                    "params": params,
+                    "policy_hash": nullable_hash_to_json(policy),
+                }),
+            )
+        }
+        conway::GovAction::HardForkInitiation(prev, version) => (
+            "hard_fork_initiation",
+            serde_json::json!({
+                "prev_action": nullable_gov_action_id_to_json(prev),
+                "protocol_version": [version.0, version.1],
+            }),
+        ),
+        conway::GovAction::TreasuryWithdrawals(withdrawals, policy) => {
+            let withdrawals: HashMap<String, String> = withdrawals
+                .iter()
+                .map(|(k, v)| (hex::encode(k.to_vec()), v.to_string()))
+                .collect();
+
+            (
+                "treasury_withdrawals",
+                serde_json::json!({
+                    "withdrawals": withdrawals,
+                    "policy_hash": nullable_hash_to_json(policy),
+                }),
+            )
+        }
+        conway::GovAction::NoConfidence(prev) => (
+            "no_confidence",
+            serde_json::json!({
+                "prev_action": nullable_gov_action_id_to_json(prev),
+            }),
+        ),
+        conway::GovAction::UpdateCommittee(prev, remove, add, quorum) => {
+            let remove: Vec<String> = remove.iter().map(credential_to_json_key).collect();
+            let add: HashMap<String, u64> = add
+                .iter()
+                .map(|(k, v)| (credential_to_json_key(k), *v))
+                .collect();
+
+            (
+                "update_committee",
+                serde_json::json!({
+                    "prev_action": nullable_gov_action_id_to_json(prev),
+                    "remove": remove,
+                    "add": add,
+                    "quorum": {
+                        "numerator": quorum.numerator,
+                        "denominator": quorum.denominator,
+                    },
+                }),
+            )
+        }
+        conway::GovAction::NewConstitution(prev, constitution) => (
+            "new_constitution",
+            serde_json::json!({
+                "prev_action": nullable_gov_action_id_to_json(prev),
+                "anchor": {
+                    "url": constitution.anchor.url,
+                    "hash": hex::encode(constitution.anchor.content_hash),
+                },
+                "guardrail_script": nullable_hash_to_json(&constitution.guardrail_script),
+            }),
+        ),
+        conway::GovAction::Information => ("info", serde_json::json!({})),
+    }
+}
+
+/// Returns the governance proposals submitted by the given transaction data.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// A row per proposal keyed by its governance action id (this transaction's
+/// hash and the proposal index, also rendered as `gov_action1...` when the
+/// index fits the single byte CIP-129 allows), with its
+/// deposit, reward account, anchor, action type (`parameter_change`,
+/// `hard_fork_initiation`, `treasury_withdrawals`, `no_confidence`,
+/// `update_committee`, `new_constitution` or `info`) and the action as JSON.
+///
+/// # Example
+///
+/// ```
+/// select p.gov_action_id, p.action from transactions, lateral tx_proposals(transactions.body) as p where p.action_type = 'treasury_withdrawals';
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_proposals(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(tx_hash, Vec<u8>),
+            name!(proposal_index, i32),
+            name!(gov_action_id, Option<String>),
+            name!(deposit, pgrx::AnyNumeric),
+            name!(reward_account, Vec<u8>),
+            name!(anchor_url, String),
+            name!(anchor_hash, Vec<u8>),
+            name!(action_type, String),
+            name!(action, pgrx::JsonB),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let tx_hash = tx.hash();
+    let proposal_procedures = tx
+        .as_conway()
+        .and_then(|x| x.transaction_body.proposal_procedures.as_ref());
+
+    let proposals_data = proposal_procedures
+        .iter()
+        .flat_map(|x| x.iter())
+        .enumerate()
+        .map(|(i, p)| {
+            let (action_type, action) = gov_action_to_json(&p.gov_action);
+
+            (
+                tx_hash.to_vec(),
+                i as i32,
+                u8::try_from(i)
+                    .ok()
+                    .map(|i| encode_gov_action_id(tx_hash.as_ref(), i)),
+                AnyNumeric::from(p.deposit),
+                p.reward_account.to_vec(),
+                p.anchor.url.clone(),
+                p.anchor.content_hash.to_vec(),
+                action_type.to_string(),
+                pgrx::JsonB(action),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(proposals_data))
+}
+
+fn treasury_donation(tx: &MultiEraTx) -> Option<u64> {
+    tx.as_conway()?
+        .transaction_body
+        .donation
+        .as_ref()
+        .map(u64::from)
+}
+
+/// Returns the lovelace donated to the treasury by the given transaction
+/// data, or NULL if it donates nothing. Donations are never zero, and the
+/// decode error sentinel is -1 so that it can't pass for one either.
+#[pg_extern(immutable)]
+fn tx_treasury_donation(tx_cbor: &[u8]) -> Option<pgrx::AnyNumeric> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(-1)),
+    };
+    let tx = tx.get();
+
+    treasury_donation(tx).map(AnyNumeric::from)
+}
+
+/// Returns the treasury value the given transaction data asserts to be
+/// current, or NULL if it doesn't assert one. The decode error sentinel is -1
+/// since an empty treasury is a valid assertion.
+#[pg_extern(immutable)]
+fn tx_current_treasury_value(tx_cbor: &[u8]) -> Option<pgrx::AnyNumeric> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(-1)),
+    };
+    let tx = tx.get();
+
+    tx.as_conway()?
+        .transaction_body
+        .treasury_value
+        .map(AnyNumeric::from)
+}
+
+/// Converts a metadatum to JSON. Text, arrays and maps with text keys map to
+/// their JSON counterparts; ints outside the `i64` range become decimal
+/// strings and bytes become `0x`-prefixed hex strings, which can't be told
+/// apart from text that happens to look the same. Maps with non-text keys
+/// become objects keyed by the JSON text of each key.
+fn metadatum_to_json(metadatum: &pallas::ledger::primitives::Metadatum) -> serde_json::Value {
+    use pallas::ledger::primitives::Metadatum;
+
+    match metadatum {
+        Metadatum::Int(x) => match i64::try_from(x.0) {
+            Ok(x) => serde_json::json!(x),
+            Err(_) => serde_json::json!(x.0.to_string()),
+        },
+        Metadatum::Bytes(x) => serde_json::json!(format!("0x{}", hex::encode(x.as_slice()))),
+        Metadatum::Text(x) => serde_json::json!(x),
+        Metadatum::Array(x) => serde_json::Value::Array(x.iter().map(metadatum_to_json).collect()),
+        Metadatum::Map(x) => serde_json::Value::Object(
+            x.iter()
+                .map(|(k, v)| {
+                    let key = match metadatum_to_json(k) {
+                        serde_json::Value::String(x) => x,
+                        other => other.to_string(),
+                    };
+
+                    (key, metadatum_to_json(v))
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Looks for a metadata label by skipping straight to the auxiliary data of a
+/// Shelley-or-later transaction instead of decoding all of it. Returns `None`
+/// when the CBOR doesn't have a shape this understands or isn't well formed
+/// all the way to its last byte, in which case the caller should fall back to
+/// a regular decode (and its error handling).
+fn scan_metadata_label(tx_cbor: &[u8], label: u64) -> Option<bool> {
+    use pallas::codec::minicbor::data::{Tag, Type};
+    use pallas::codec::minicbor::Decoder;
+
+    let mut d = Decoder::new(tx_cbor);
+
+    // [body, witness set, (is valid), auxiliary data]
+    let len = d.array().ok()??;
+    if len != 3 && len != 4 {
+        return None;
+    }
+    for _ in 0..2 {
+        if d.datatype().ok()? != Type::Map {
+            return None;
+        }
+        d.skip().ok()?;
+    }
+    if len == 4 {
+        d.bool().ok()?;
+    }
+
+    let found = match d.datatype().ok()? {
+        Type::Null => {
+            d.skip().ok()?;
+            false
+        }
+        // shelley: the metadata map itself
+        Type::Map => scan_metadata_map(&mut d, label)?,
+        // allegra / mary: [metadata, native scripts]
+        Type::Array => {
+            if d.array().ok()?? != 2 {
+                return None;
+            }
+            let found = scan_metadata_map(&mut d, label)?;
+            d.skip().ok()?;
+            found
+        }
+        // alonzo onwards: #6.259({ ? 0: metadata, ... })
+        Type::Tag => {
+            if d.tag().ok()? != Tag::new(259) {
+                return None;
+            }
+
+            let mut found = false;
+            for _ in 0..d.map().ok()?? {
+                if d.u64().ok()? == 0 {
+                    found = scan_metadata_map(&mut d, label)?;
+                } else {
+                    d.skip().ok()?;
+                }
+            }
+            found
+        }
+        _ => return None,
+    };
+
+    // Anything left over means this isn't a transaction after all.
+    if d.position() != tx_cbor.len() {
+        return None;
+    }
+
+    Some(found)
+}
+
+/// Skips a metadata map, returning whether it has the given label.
+fn scan_metadata_map(d: &mut pallas::codec::minicbor::Decoder, label: u64) -> Option<bool> {
+    let mut found = false;
+    for _ in 0..d.map().ok()?? {
+        if d.u64().ok()? == label {
+            found = true;
+        }
+        d.skip().ok()?;
+    }
+
+    Some(found)
+}
+
+/// Returns the metadata of the given transaction data.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The transaction data in CBOR format.
+///
+/// # Returns
+///
+/// The metadata as a JSON object keyed by label (see `metadatum_to_json` for
+/// how values are mapped), or NULL if the transaction has no metadata.
+///
+/// # Example
+///
+/// ```
+/// select tx_metadata(body) from transactions;
+/// ```
+#[pg_extern(immutable)]
+fn tx_metadata(tx_cbor: &[u8]) -> Option<pgrx::JsonB> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, None).flatten(),
+    };
+    let tx = tx.get();
+
+    let metadata = tx.metadata();
+    let metadata = metadata.as_alonzo()?;
+
+    if metadata.is_empty() {
+        return None;
+    }
+
+    let metadata_data: serde_json::Map<String, serde_json::Value> = metadata
+        .iter()
+        .map(|(k, v)| (k.to_string(), metadatum_to_json(v)))
+        .collect();
+
+    Some(pgrx::JsonB(serde_json::Value::Object(metadata_data)))
+}
+
+#[pg_extern(immutable)]
+fn tx_metadata_label(tx_cbor: &[u8], label: i64) -> Option<pgrx::JsonB> {
+    let label = match u64::try_from(label) {
+        Ok(x) => x,
+        Err(_) => {
+            return on_invalid_argument(format!("invalid metadata label: {}", label), None)
+                .flatten()
+        }
+    };
+
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, None).flatten(),
+    };
+    let tx = tx.get();
+
+    let metadatum = tx.metadata().find(label).map(metadatum_to_json)?;
+
+    Some(pgrx::JsonB(metadatum))
+}
+
+#[pg_extern(immutable)]
+fn tx_has_metadata_label(tx_cbor: &[u8], label: i64) -> Option<bool> {
+    let label = match u64::try_from(label) {
+        Ok(x) => x,
+        Err(_) => return on_invalid_argument(format!("invalid metadata label: {}", label), false),
+    };
+
+    // Skipping the full decode is what makes this usable as a filter over
+    // large tables, at the cost of not validating the rest of the CBOR.
+    if let Some(found) = scan_metadata_label(tx_cbor, label) {
+        return Some(found);
+    }
+
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(tx.metadata().find(label).is_some())
+}
+
+/// The transaction metadata label used by CIP-25 for NFT metadata.
+const CIP25_LABEL: u64 = 721;
+
+/// Returns the CIP-25 metadata of every asset minted by a transaction, as
+/// `(policy_id, asset_name, metadata)` with the metadata as JSON. Both the v1
+/// layout (policy ids as hex text, asset names as utf8 or hex text) and the v2
+/// layout (raw bytes keys) are understood; minted assets without metadata are
+/// left out, as are burns.
+fn cip25_minted_assets(tx: &MultiEraTx) -> Vec<(Vec<u8>, Vec<u8>, serde_json::Value)> {
+    use pallas::ledger::primitives::Metadatum;
+
+    let metadata = tx.metadata();
+    let entries = match metadata.find(CIP25_LABEL) {
+        Some(Metadatum::Map(x)) => x,
+        _ => return vec![],
+    };
+
+    let key_matches = |key: &Metadatum, bytes: &[u8], allow_utf8: bool| match key {
+        Metadatum::Bytes(x) => x.as_slice() == bytes,
+        Metadatum::Text(x) => {
+            (allow_utf8 && x.as_bytes() == bytes) || x.eq_ignore_ascii_case(&hex::encode(bytes))
+        }
+        _ => false,
+    };
+
+    let mut minted = vec![];
+
+    for policy in tx.mints() {
+        let policy_id = policy.policy().as_ref();
+
+        let assets = match entries
+            .iter()
+            .find(|(k, _)| key_matches(k, policy_id, false))
+        {
+            Some((_, Metadatum::Map(x))) => x,
+            _ => continue,
+        };
+
+        for asset in policy.assets() {
+            if asset.any_coin() <= 0 {
+                continue;
+            }
+
+            if let Some((_, metadatum)) = assets
+                .iter()
+                .find(|(k, _)| key_matches(k, asset.name(), true))
+            {
+                minted.push((
+                    policy_id.to_vec(),
+                    asset.name().to_vec(),
+                    metadatum_to_json(metadatum),
+                ));
+            }
+        }
+    }
+
+    minted
+}
+
+/// Reads a CIP-25 string property, which may be split into an array of
+/// strings to get around the 64 bytes limit of metadata strings.
+fn cip25_string(value: Option<&serde_json::Value>) -> Option<String> {
+    match value? {
+        serde_json::Value::String(x) => Some(x.clone()),
+        serde_json::Value::Array(x) => x.iter().map(|x| x.as_str()).collect(),
+        _ => None,
+    }
+}
+
+/// Returns the CIP-25 (label 721) NFT metadata of the assets minted by a
+/// transaction.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The CBOR of the transaction.
+///
+/// # Returns
+///
+/// One row per minted asset that has metadata, with its policy id, asset name,
+/// `name`, `image` (string arrays concatenated), `mediaType`, `files` and the
+/// whole metadata of the asset as JSON. Both the v1 (hex/utf8 text keys) and
+/// the v2 (bytes keys) layouts are supported. Burned assets are not listed.
+///
+/// # Example
+///
+/// ```
+/// select (tx_mint_cip25(body)).* from transactions where tx_has_mint_cip25(body);
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn tx_mint_cip25(
+    tx_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(policy_id, Vec<u8>),
+            name!(asset_name, Vec<u8>),
+            name!(name, Option<String>),
+            name!(image, Option<String>),
+            name!(media_type, Option<String>),
+            name!(files, Option<pgrx::JsonB>),
+            name!(metadata, pgrx::JsonB),
+        ),
+    >,
+> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let tx = tx.get();
+
+    let rows: Vec<_> = cip25_minted_assets(tx)
+        .into_iter()
+        .map(|(policy_id, asset_name, metadata)| {
+            (
+                policy_id,
+                asset_name,
+                cip25_string(metadata.get("name")),
+                cip25_string(metadata.get("image")),
+                cip25_string(metadata.get("mediaType")),
+                metadata.get("files").cloned().map(pgrx::JsonB),
+                pgrx::JsonB(metadata),
+            )
+        })
+        .collect();
+
+    Some(TableIterator::new(rows))
+}
+
+/// Returns whether a transaction mints any asset with CIP-25 (label 721) NFT
+/// metadata, in either the v1 or v2 layout.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The CBOR of the transaction.
+///
+/// # Returns
+///
+/// True if `tx_mint_cip25` would return at least one row.
+///
+/// # Example
+///
+/// ```
+/// select tx_hash(body) from transactions where tx_has_mint_cip25(body);
+/// ```
+#[pg_extern(immutable)]
+fn tx_has_mint_cip25(tx_cbor: &[u8]) -> Option<bool> {
+    // Most transactions don't carry label 721 at all, which can be told
+    // without a full decode.
+    if let Some(false) = scan_metadata_label(tx_cbor, CIP25_LABEL) {
+        return Some(false);
+    }
+
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(!cip25_minted_assets(tx).is_empty())
+}
+
+#[pg_extern(immutable)]
+fn tx_hash_is(tx_cbor: &[u8], hash: &[u8]) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(tx.hash().to_vec().eq(&hash))
+}
+
+#[pg_extern(immutable)]
+fn tx_has_mint(tx_cbor: &[u8]) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(!tx.mints().is_empty())
+}
+
+#[pg_extern(immutable)]
+fn tx_has_address_output(tx_cbor: &[u8], address: &[u8]) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(tx.outputs().iter().any(|o| {
+        o.address()
+            .ok()
+            .map(|iter_address| iter_address.to_vec().eq(address))
+            .unwrap_or(false)
+    }))
+}
+
+#[pg_extern(immutable)]
+fn tx_has_policy_id_output(tx_cbor: &[u8], policy_id: &[u8]) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(tx.outputs().iter().any(|o| {
+        o.value()
+            .assets()
+            .to_vec()
+            .iter()
+            .any(|a| a.policy().deref().eq(&policy_id))
+    }))
+}
+
+#[pg_extern(immutable)]
+fn tx_has_policy_id_mint(tx_cbor: &[u8], policy_id: &[u8]) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(tx.mints().iter().any(|m| m.policy().deref().eq(&policy_id)))
+}
+
+#[pg_extern(immutable)]
+fn tx_has_subject_output(tx_cbor: &[u8], subject: &[u8]) -> Option<bool> {
+    match asset_subject::from_bytes(subject) {
+        Ok(x) => tx_has_asset_subject_output(tx_cbor, x),
+        Err(err) => on_invalid_argument(err, false),
+    }
+}
+
+/// Like `tx_has_subject_output`, but takes an `asset_subject`.
+#[pg_extern(immutable)]
+fn tx_has_asset_subject_output(tx_cbor: &[u8], subject: asset_subject) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    Some(tx.produces().iter().any(|(_, o)| {
+        o.value().assets().iter().any(|p| {
+            p.assets()
+                .iter()
+                .any(|a| subject.matches(p.policy().as_ref(), a.name()))
+        })
+    }))
+}
+
+#[pg_extern(immutable)]
+fn tx_has_mint_output(tx_cbor: &[u8], subject: &[u8]) -> Option<bool> {
+    match asset_subject::from_bytes(subject) {
+        Ok(x) => tx_has_asset_subject_mint(tx_cbor, x),
+        Err(err) => on_invalid_argument(err, false),
+    }
+}
+
+/// Like `tx_has_mint_output`, but takes an `asset_subject`.
+#[pg_extern(immutable)]
+fn tx_has_asset_subject_mint(tx_cbor: &[u8], subject: asset_subject) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+
+    Some(subject.mint_amount(tx.get()).is_some())
+}
+
+/// Returns true if the transaction produces an output holding the asset, or
+/// mints (or burns) it. A transaction with invalid scripts only produces its
+/// collateral return and doesn't mint or burn anything.
+///
+/// # Arguments
+///
+/// * `tx_cbor` - The CBOR of the transaction.
+/// * `fingerprint` - The asset, usually given as its `asset1...` fingerprint.
+///
+/// # Example
+///
+/// ```
+/// select slot from txs where tx_has_asset_fingerprint(cbor, 'asset1rjklcrnsdzqp65wjgrg55sy9723kw09mlgvlc3');
+/// ```
+#[pg_extern(immutable)]
+fn tx_has_asset_fingerprint(tx_cbor: &[u8], fingerprint: asset_subject) -> Option<bool> {
+    let tx = match decode_tx(tx_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let tx = tx.get();
+
+    let in_outputs = tx
+        .produces()
+        .iter()
+        .any(|(_, o)| fingerprint.output_amount(o) != 0);
+
+    Some(in_outputs || (tx.is_valid() && fingerprint.mint_amount(tx).is_some()))
+}
+
+#[pg_extern(immutable)]
+fn address_network_id(address: &[u8]) -> Option<i64> {
+    let address = match Address::from_bytes(address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, -1),
+    };
+
+    match address.network() {
+        Some(n) => Some(n.value() as i64),
+        None => Some(-1),
+    }
+}
+
+#[pg_extern(immutable)]
+fn address_payment_part(address: &[u8]) -> Option<Vec<u8>> {
+    let address = match Address::from_bytes(address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+
+    let payment_part = match address {
+        Address::Shelley(a) => a.payment().to_vec(),
+        Address::Byron(_) => {
+            vec![]
+        }
+        _ => return Some(vec![]),
+    };
+
+    Some(payment_part)
+}
+
+#[pg_extern(immutable)]
+fn address_stake_part(address: &[u8]) -> Option<Vec<u8>> {
+    let address = match Address::from_bytes(address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+
+    let stake_part = match address {
+        Address::Shelley(a) => a.delegation().to_vec(),
+        Address::Byron(_) => {
+            vec![]
+        }
+        _ => return Some(vec![]),
+    };
+
+    Some(stake_part)
+}
+
+extension_sql!(
+    r#"
+CREATE TYPE address_info AS (
+    address_type integer,
+    network_id integer,
+    payment_kind text,
+    payment_hash bytea,
+    delegation_kind text,
+    delegation_hash bytea,
+    pointer_slot bigint,
+    pointer_tx_idx bigint,
+    pointer_cert_idx bigint,
+    byron_type text,
+    byron_root bytea,
+    byron_attributes jsonb,
+    byron_protocol_magic bigint
+);
+"#,
+    name = "address_info_type",
+);
+
+/// Describes the attributes of a Byron address payload. The derivation path
+/// holds the CBOR encoded ciphertext and the network tag the CBOR encoded
+/// protocol magic, which is only present on testnets.
+fn byron_attributes_to_json(
+    payload: &pallas::ledger::addresses::byron::AddressPayload,
+) -> serde_json::Value {
+    use pallas::ledger::addresses::byron::{AddrAttrProperty, AddrDistr};
+
+    let mut attributes = serde_json::Map::new();
+
+    for property in payload.attributes.iter() {
+        match property {
+            AddrAttrProperty::AddrDistr(AddrDistr::BootstrapEraDistribution) => {
+                attributes.insert("stake_distribution".into(), "bootstrap".into());
+            }
+            AddrAttrProperty::AddrDistr(AddrDistr::SingleKeyDistribution(x)) => {
+                attributes.insert(
+                    "stake_distribution".into(),
+                    serde_json::json!({ "single_key": hex::encode(x) }),
+                );
+            }
+            AddrAttrProperty::DerivationPath(x) => {
+                attributes.insert(
+                    "derivation_path".into(),
+                    byron_derivation_path(x.as_slice()).into(),
+                );
+            }
+            AddrAttrProperty::NetworkTag(x) => {
+                attributes.insert(
+                    "protocol_magic".into(),
+                    byron_protocol_magic(x.as_slice()).into(),
+                );
+            }
+        }
+    }
+
+    serde_json::Value::Object(attributes)
+}
+
+fn byron_derivation_path(derivation_path: &[u8]) -> Option<String> {
+    pallas::codec::minicbor::Decoder::new(derivation_path)
+        .bytes()
+        .ok()
+        .map(hex::encode)
+}
+
+fn byron_protocol_magic(network_tag: &[u8]) -> Option<i64> {
+    pallas::codec::minicbor::decode::<u32>(network_tag)
+        .ok()
+        .map(|x| x as i64)
+}
+
+/// Returns the CIP-19 type of an address, the upper nibble of its header
+/// byte (`8` for Byron addresses).
+///
+/// # Arguments
+///
+/// * `address` - The address in byte array format.
+///
+/// # Returns
+///
+/// The address type, from 0 to 15.
+///
+/// # Example
+///
+/// ```
+/// select address_type(address) from utxos;
+/// ```
+#[pg_extern(immutable)]
+fn address_type(address: &[u8]) -> Option<i32> {
+    let address = match Address::from_bytes(address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, -1),
+    };
+
+    Some(address.typeid() as i32)
+}
+
+/// Decomposes an address into its CIP-19 parts.
+///
+/// # Arguments
+///
+/// * `address` - The address in byte array format.
+///
+/// # Returns
+///
+/// An `address_info` record. Only the fields that apply to the kind of
+/// address are set: Shelley addresses fill the payment and delegation parts
+/// (with the pointer fields for pointer addresses), stake addresses only the
+/// delegation part and Byron addresses the `byron_*` fields.
+///
+/// # Example
+///
+/// ```
+/// select (address_info(address)).* from utxos;
+/// ```
+#[pg_extern(immutable, requires = ["address_info_type"])]
+fn address_info(address: &[u8]) -> Option<pgrx::composite_type!('static, "address_info")> {
+    use pallas::ledger::addresses::{ShelleyDelegationPart, StakePayload};
+
+    let address = match Address::from_bytes(address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, None).flatten(),
+    };
+
+    let mut record = PgHeapTuple::new_composite_type("address_info").unwrap();
+    record
+        .set_by_name("address_type", address.typeid() as i32)
+        .unwrap();
+    record
+        .set_by_name("network_id", address.network().map(|x| x.value() as i32))
+        .unwrap();
+
+    match &address {
+        Address::Shelley(a) => {
+            let payment_kind = if a.payment().is_script() {
+                "script"
+            } else {
+                "key"
+            };
+            record.set_by_name("payment_kind", payment_kind).unwrap();
+            record
+                .set_by_name("payment_hash", a.payment().as_hash().to_vec())
+                .unwrap();
+
+            let delegation_kind = match a.delegation() {
+                ShelleyDelegationPart::Key(_) => "key",
+                ShelleyDelegationPart::Script(_) => "script",
+                ShelleyDelegationPart::Pointer(_) => "pointer",
+                ShelleyDelegationPart::Null => "none",
+            };
+            record
+                .set_by_name("delegation_kind", delegation_kind)
+                .unwrap();
+            record
+                .set_by_name(
+                    "delegation_hash",
+                    a.delegation().as_hash().map(|x| x.to_vec()),
+                )
+                .unwrap();
+
+            if let ShelleyDelegationPart::Pointer(p) = a.delegation() {
+                record.set_by_name("pointer_slot", p.slot() as i64).unwrap();
+                record
+                    .set_by_name("pointer_tx_idx", p.tx_idx() as i64)
+                    .unwrap();
+                record
+                    .set_by_name("pointer_cert_idx", p.cert_idx() as i64)
+                    .unwrap();
+            }
+        }
+        Address::Stake(a) => {
+            let delegation_kind = match a.payload() {
+                StakePayload::Stake(_) => "key",
+                StakePayload::Script(_) => "script",
+            };
+            record
+                .set_by_name("delegation_kind", delegation_kind)
+                .unwrap();
+            record
+                .set_by_name("delegation_hash", a.payload().as_hash().to_vec())
+                .unwrap();
+        }
+        Address::Byron(a) => {
+            use pallas::ledger::addresses::byron::{AddrAttrProperty, AddrType};
+
+            let payload = match a.decode() {
+                Ok(x) => x,
+                Err(err) => return on_decode_error(err, None).flatten(),
+            };
+
+            let byron_type = match payload.addrtype {
+                AddrType::PubKey => "pubkey".to_string(),
+                AddrType::Script => "script".to_string(),
+                AddrType::Redeem => "redeem".to_string(),
+                AddrType::Other(x) => x.to_string(),
+            };
+
+            let protocol_magic = payload.attributes.iter().find_map(|x| match x {
+                AddrAttrProperty::NetworkTag(x) => byron_protocol_magic(x.as_slice()),
+                _ => None,
+            });
+
+            record.set_by_name("byron_type", byron_type).unwrap();
+            record
+                .set_by_name("byron_root", payload.root.to_vec())
+                .unwrap();
+            record
+                .set_by_name(
+                    "byron_attributes",
+                    pgrx::JsonB(byron_attributes_to_json(&payload)),
+                )
+                .unwrap();
+            record
+                .set_by_name("byron_protocol_magic", protocol_magic)
+                .unwrap();
+        }
+    }
+
+    Some(record)
+}
+
+/// Returns the `(slot, tx_index, cert_index)` pointer of a pointer address.
+///
+/// # Arguments
+///
+/// * `address` - The address in byte array format.
+///
+/// # Returns
+///
+/// A single row with the pointer, or no rows if the address isn't a pointer
+/// address.
+///
+/// # Example
+///
+/// ```
+/// select p.* from utxos, address_pointer(address) p;
+/// ```
+#[pg_extern(immutable)]
+#[allow(clippy::type_complexity)]
+fn address_pointer(
+    address: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(slot, i64),
+            name!(tx_index, i64),
+            name!(cert_index, i64),
+        ),
+    >,
+> {
+    use pallas::ledger::addresses::ShelleyDelegationPart;
+
+    let address = match Address::from_bytes(address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+
+    let pointer = match address {
+        Address::Shelley(a) => match a.delegation() {
+            ShelleyDelegationPart::Pointer(p) => {
+                Some((p.slot() as i64, p.tx_idx() as i64, p.cert_idx() as i64))
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+
+    Some(TableIterator::new(pointer))
+}
+
+/// Finds the stake credential registered by the certificate at `cert_index`
+/// of the transaction at `tx_index` of a block, as its hash and whether that
+/// is a script hash.
+fn pointer_credential(
+    block: &MultiEraBlock,
+    tx_index: u64,
+    cert_index: u64,
+) -> Option<(Vec<u8>, bool)> {
+    let tx = block.txs().into_iter().nth(tx_index as usize)?;
+    let cert = tx.certs().into_iter().nth(cert_index as usize)?;
+    let info = certificate_info(&cert)?;
+
+    let is_registration = matches!(
+        info.kind,
+        "stake_registration"
+            | "reg"
+            | "stake_reg_deleg"
+            | "vote_reg_deleg"
+            | "stake_vote_reg_deleg"
+    );
+
+    if is_registration {
+        Some((info.credential?, info.credential_is_script?))
+    } else {
+        None
+    }
+}
+
+/// Resolves the stake credential of a pointer address by looking up the
+/// registration certificate it points to.
+///
+/// # Arguments
+///
+/// * `address` - The address in byte array format.
+/// * `blocks` - A table (or view) with a row per block, holding its slot in an
+///   integer column and its CBOR in a `bytea` one.
+/// * `slot_column` - The name of the slot column.
+/// * `cbor_column` - The name of the CBOR column.
+///
+/// # Returns
+///
+/// A single row with the 28 bytes stake key or script hash registered by the
+/// certificate and whether it is a script hash, or no rows if the address
+/// isn't a pointer address or the pointer doesn't reference a stake
+/// registration in the table.
+///
+/// # Example
+///
+/// ```
+/// select u.address, p.* from utxos u, resolve_pointer_address(u.address, 'blocks') p where address_type(u.address) in (4, 5);
+/// ```
+#[pg_extern(stable)]
+fn resolve_pointer_address(
+    address: &[u8],
+    blocks: pgrx::PgRelation,
+    slot_column: default!(&str, "'slot'"),
+    cbor_column: default!(&str, "'cbor'"),
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(credential, Vec<u8>),
+            name!(credential_is_script, bool),
+        ),
+    >,
+> {
+    use pallas::ledger::addresses::ShelleyDelegationPart;
+
+    let address = match Address::from_bytes(address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+
+    let pointer = match address {
+        Address::Shelley(a) => match a.delegation() {
+            ShelleyDelegationPart::Pointer(p) => p.clone(),
+            _ => return Some(TableIterator::new(std::iter::empty())),
+        },
+        _ => return Some(TableIterator::new(std::iter::empty())),
+    };
+
+    let query = format!(
+        "SELECT {} FROM {} WHERE {} = $1",
+        spi::quote_identifier(cbor_column),
+        spi::quote_qualified_identifier(blocks.namespace(), blocks.name()),
+        spi::quote_identifier(slot_column),
+    );
+
+    let block_cbor = Spi::connect(|client| {
+        let args = vec![(
+            PgBuiltInOids::INT8OID.oid(),
+            (pointer.slot() as i64).into_datum(),
+        )];
+        let table = client.select(&query, Some(1), Some(args))?;
+
+        if table.is_empty() {
+            Ok(None)
+        } else {
+            table.first().get_one::<Vec<u8>>()
+        }
+    });
+
+    let block_cbor = match block_cbor {
+        Ok(Some(x)) => x,
+        Ok(None) => return Some(TableIterator::new(std::iter::empty())),
+        Err(err) => {
+            return on_invalid_argument(
+                format!("failed to query {}: {}", blocks.name(), err),
+                TableIterator::new(std::iter::empty()),
+            )
+        }
+    };
+
+    let block = match decode_block(&block_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+
+    let credential = pointer_credential(block.get(), pointer.tx_idx(), pointer.cert_idx());
+
+    Some(TableIterator::new(credential))
+}
+
+#[pg_extern(immutable)]
+fn address_to_bytes(address: String) -> Option<Vec<u8>> {
+    let address = match Address::from_bech32(&address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+
+    Some(address.to_vec())
+}
+
+/// Parses an address from its text representation.
+///
+/// # Arguments
+///
+/// * `address` - A bech32 Shelley or stake address, or a base58 Byron address.
+///
+/// # Returns
+///
+/// The address in byte array format.
+///
+/// # Example
+///
+/// ```
+/// select * from utxos where address = address_from_string('DdzFFzCqrht...');
+/// ```
+#[pg_extern(immutable)]
+fn address_from_string(address: &str) -> Option<Vec<u8>> {
+    use pallas::ledger::addresses::ByronAddress;
+
+    let address = match Address::from_bech32(address) {
+        Ok(x) => x,
+        Err(_) => match ByronAddress::from_base58(address).and_then(|x| x.decode().map(|_| x)) {
+            Ok(x) => Address::Byron(x),
+            Err(err) => return on_decode_error(err, vec![]),
+        },
+    };
+
+    Some(address.to_vec())
+}
+
+#[pg_extern(immutable)]
+fn address_is_byron(address: &[u8]) -> Option<bool> {
+    let address = match Address::from_bytes(address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+
+    Some(matches!(address, Address::Byron(_)))
+}
+
+/// Returns the attributes of a Byron address.
+///
+/// # Arguments
+///
+/// * `address` - The address in byte array format.
+///
+/// # Returns
+///
+/// A JSON object with the `derivation_path` ciphertext of HD wallet addresses
+/// as hex, the `protocol_magic` of testnet addresses and the
+/// `stake_distribution`, each only present if the address sets it. NULL for
+/// Shelley and stake addresses.
+///
+/// # Example
+///
+/// ```
+/// select byron_address_attributes(address) from utxos where address_is_byron(address);
+/// ```
+#[pg_extern(immutable)]
+fn byron_address_attributes(address: &[u8]) -> Option<pgrx::JsonB> {
+    let address = match Address::from_bytes(address) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, None).flatten(),
+    };
+
+    let payload = match address {
+        Address::Byron(x) => x.decode(),
+        _ => return None,
+    };
+
+    match payload {
+        Ok(x) => Some(pgrx::JsonB(byron_attributes_to_json(&x))),
+        Err(err) => on_decode_error(err, None).flatten(),
+    }
+}
+
+#[pg_extern(immutable)]
+fn address_to_bech32(address_bytes: &[u8]) -> Option<String> {
+    let address = match Address::from_bytes(address_bytes) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, String::new()),
+    };
+
+    match address {
+        // Byron addresses have no bech32 form, base58 is their canonical text
+        // encoding.
+        Address::Byron(x) => Some(x.to_base58()),
+        _ => match address.to_bech32() {
+            Ok(x) => Some(x),
+            Err(err) => on_decode_error(err, String::new()),
+        },
+    }
+}
+
+/// Validates the network id and credentials used to build an address.
+fn address_network(network_id: i32) -> Result<pallas::ledger::addresses::Network, String> {
+    match u8::try_from(network_id) {
+        Ok(x) if x <= 0x0f => Ok(x.into()),
+        _ => Err(format!("invalid network id: {}", network_id)),
+    }
+}
+
+fn address_credential_hash(hash: &[u8]) -> Result<pallas::crypto::hash::Hash<28>, String> {
+    <[u8; 28]>::try_from(hash)
+        .map(pallas::crypto::hash::Hash::new)
+        .map_err(|_| format!("invalid credential hash: {}", hex::encode(hash)))
+}
+
+fn shelley_address(
+    network_id: i32,
+    payment_hash: &[u8],
+    payment_is_script: bool,
+    stake_hash: Option<&[u8]>,
+    stake_is_script: bool,
+) -> Result<pallas::ledger::addresses::ShelleyAddress, String> {
+    use pallas::ledger::addresses::{ShelleyAddress, ShelleyDelegationPart, ShelleyPaymentPart};
+
+    let network = address_network(network_id)?;
+
+    let payment_hash = address_credential_hash(payment_hash)?;
+    let payment = if payment_is_script {
+        ShelleyPaymentPart::script_hash(payment_hash)
+    } else {
+        ShelleyPaymentPart::key_hash(payment_hash)
+    };
+
+    let delegation = match stake_hash.map(address_credential_hash).transpose()? {
+        Some(x) if stake_is_script => ShelleyDelegationPart::script_hash(x),
+        Some(x) => ShelleyDelegationPart::key_hash(x),
+        None => ShelleyDelegationPart::Null,
+    };
+
+    Ok(ShelleyAddress::new(network, payment, delegation))
+}
+
+/// Builds a Shelley address from its credentials.
+///
+/// # Arguments
+///
+/// * `network_id` - The network id (0 for testnets, 1 for mainnet).
+/// * `payment_hash` - The 28 bytes payment key or script hash.
+/// * `payment_is_script` - Whether the payment credential is a script hash.
+/// * `stake_hash` - The 28 bytes stake key or script hash, NULL for an
+///   enterprise address.
+/// * `stake_is_script` - Whether the stake credential is a script hash.
+///
+/// # Returns
+///
+/// The address in byte array format.
+///
+/// # Example
+///
+/// ```
+/// select address_to_bech32(address_from_parts(1, payment_hash, false, stake_hash, false)) from wallets;
+/// ```
+#[pg_extern(immutable)]
+fn address_from_parts(
+    network_id: i32,
+    payment_hash: &[u8],
+    payment_is_script: default!(bool, false),
+    stake_hash: default!(Option<&[u8]>, "NULL"),
+    stake_is_script: default!(bool, false),
+) -> Option<Vec<u8>> {
+    match shelley_address(
+        network_id,
+        payment_hash,
+        payment_is_script,
+        stake_hash,
+        stake_is_script,
+    ) {
+        Ok(x) => Some(x.to_vec()),
+        Err(err) => on_invalid_argument(err, vec![]),
+    }
+}
+
+/// Builds a stake (reward) address from a stake credential.
+///
+/// # Arguments
+///
+/// * `network_id` - The network id (0 for testnets, 1 for mainnet).
+/// * `hash` - The 28 bytes stake key or script hash.
+/// * `is_script` - Whether the credential is a script hash.
+///
+/// # Returns
+///
+/// The stake address in byte array format.
+///
+/// # Example
+///
+/// ```
+/// select stake_part_to_bech32(stake_address_from_credential(1, credential, credential_is_script)) from tx_certificates(tx_cbor);
+/// ```
+#[pg_extern(immutable)]
+fn stake_address_from_credential(
+    network_id: i32,
+    hash: &[u8],
+    is_script: default!(bool, false),
+) -> Option<Vec<u8>> {
+    // A stake address is the delegation part of a base address, so any
+    // payment credential will do.
+    let stake_address = shelley_address(network_id, &[0; 28], false, Some(hash), is_script)
+        .and_then(|x| StakeAddress::try_from(x).map_err(|err| err.to_string()));
+
+    match stake_address {
+        Ok(x) => Some(x.to_vec()),
+        Err(err) => on_invalid_argument(err, vec![]),
+    }
+}
+
+/// Returns the stake address of a Shelley address in bech32.
+///
+/// # Arguments
+///
+/// * `address_bytes` - The address in byte array format.
+///
+/// # Returns
+///
+/// The bech32 stake address, or an empty string for Byron addresses and
+/// Shelley addresses without a stake key or script (enterprise and pointer
+/// addresses).
+///
+/// # Example
+///
+/// ```
+/// select address_to_stake_part_bech32(address) from utxos;
+/// ```
+#[pg_extern(immutable)]
+fn address_to_stake_part_bech32(address_bytes: &[u8]) -> Option<String> {
+    let address = match Address::from_bytes(address_bytes) {
+        Ok(addr) => addr,
+        Err(err) => return on_decode_error(err, String::new()),
+    };
+
+    let stake_address = match address {
+        Address::Shelley(a) => match StakeAddress::try_from(a) {
+            Ok(x) => x,
+            Err(_) => return Some(String::new()),
+        },
+        _ => return Some(String::new()),
+    };
+
+    match stake_address.to_bech32() {
+        Ok(x) => Some(x),
+        Err(err) => on_decode_error(err, String::new()),
+    }
+}
+
+/// Returns a stake address in bech32.
+///
+/// # Arguments
+///
+/// * `stake_part_bytes` - The stake address in byte array format.
+///
+/// # Returns
+///
+/// The bech32 `stake1...` / `stake_test1...` address.
+///
+/// # Example
+///
+/// ```
+/// select stake_part_to_bech32(stake_address_from_credential(1, credential)) from credentials;
+/// ```
+#[pg_extern(immutable)]
+fn stake_part_to_bech32(stake_part_bytes: &[u8]) -> Option<String> {
+    let stake_part = match Address::from_bytes(stake_part_bytes) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, String::new()),
+    };
+
+    match stake_part.to_bech32() {
+        Ok(x) => Some(x),
+        Err(err) => on_decode_error(err, String::new()),
+    }
+}
+
+/// A kind of governance credential identifier, as described by CIP-129 and
+/// its CIP-105 predecessor.
+struct GovernanceIdKind {
+    hrp: &'static str,
+    /// The upper nibble of the CIP-129 header byte.
+    key_type: u8,
+    /// The CIP-105 prefix for script credentials, which CIP-129 folded into
+    /// the header byte.
+    script_hrp: &'static str,
+}
+
+const DREP_ID: GovernanceIdKind = GovernanceIdKind {
+    hrp: "drep",
+    key_type: 0x2,
+    script_hrp: "drep_script",
+};
+
+const CC_HOT_ID: GovernanceIdKind = GovernanceIdKind {
+    hrp: "cc_hot",
+    key_type: 0x0,
+    script_hrp: "cc_hot_script",
+};
+
+const CC_COLD_ID: GovernanceIdKind = GovernanceIdKind {
+    hrp: "cc_cold",
+    key_type: 0x1,
+    script_hrp: "cc_cold_script",
+};
+
+/// The lower nibble of a CIP-129 header byte for key hash credentials.
+const CIP129_KEY_HASH: u8 = 0x2;
+/// The lower nibble of a CIP-129 header byte for script hash credentials.
+const CIP129_SCRIPT_HASH: u8 = 0x3;
+
+impl GovernanceIdKind {
+    /// Builds the CIP-129 bytes (header byte followed by the hash) of a
+    /// credential.
+    fn credential_id(&self, credential: &[u8], is_script: bool) -> Result<Vec<u8>, String> {
+        if credential.len() != 28 {
+            return Err(format!(
+                "expected a 28 bytes {} credential, got {} bytes",
+                self.hrp,
+                credential.len()
+            ));
+        }
+
+        let credential_type = if is_script {
+            CIP129_SCRIPT_HASH
+        } else {
+            CIP129_KEY_HASH
+        };
+
+        let mut id = vec![self.key_type << 4 | credential_type];
+        id.extend_from_slice(credential);
+        Ok(id)
+    }
+
+    fn to_bech32(&self, id: &[u8]) -> Result<String, String> {
+        let valid = id.len() == 29
+            && id[0] >> 4 == self.key_type
+            && matches!(id[0] & 0x0f, CIP129_KEY_HASH | CIP129_SCRIPT_HASH);
+
+        if !valid {
+            return Err(format!(
+                "invalid CIP-129 {} id: {}",
+                self.hrp,
+                hex::encode(id)
+            ));
+        }
+
+        bech32::encode(self.hrp, id.to_base32(), bech32::Variant::Bech32).map_err(|x| x.to_string())
+    }
+
+    /// Parses a CIP-129 identifier, or a CIP-105 one which is converted to
+    /// its CIP-129 bytes.
+    fn parse_bech32(&self, bech32: &str) -> Result<Vec<u8>, String> {
+        let (hrp, data, _) = bech32::decode(bech32).map_err(|x| x.to_string())?;
+        let bytes = Vec::from_base32(&data).map_err(|x| x.to_string())?;
+
+        match (hrp.as_str(), bytes.len()) {
+            (hrp, 29) if hrp == self.hrp => {
+                self.to_bech32(&bytes)?;
+                Ok(bytes)
+            }
+            (hrp, 28) if hrp == self.hrp => self.credential_id(&bytes, false),
+            (hrp, 28) if hrp == self.script_hrp => self.credential_id(&bytes, true),
+            _ => Err(format!("invalid {} id: {}", self.hrp, bech32)),
+        }
+    }
+}
+
+/// Encodes a DRep as a CIP-129 `drep1...` identifier.
+///
+/// # Arguments
+///
+/// * `drep_id` - The 29 bytes CIP-129 id: a header byte (`0x22` for key
+///   hashes, `0x23` for script hashes) followed by the credential.
+///
+/// # Returns
+///
+/// The bech32 identifier.
+///
+/// # Example
+///
+/// ```
+/// select drep_to_bech32(drep_id) from dreps;
+/// ```
+#[pg_extern(immutable)]
+fn drep_to_bech32(drep_id: &[u8]) -> Option<String> {
+    DREP_ID
+        .to_bech32(drep_id)
+        .map_or_else(|err| on_invalid_argument(err, String::new()), Some)
+}
+
+#[pg_extern(immutable, name = "drep_to_bech32")]
+fn drep_credential_to_bech32(credential: &[u8], is_script: bool) -> Option<String> {
+    DREP_ID
+        .credential_id(credential, is_script)
+        .and_then(|x| DREP_ID.to_bech32(&x))
+        .map_or_else(|err| on_invalid_argument(err, String::new()), Some)
+}
+
+/// Decodes a `drep1...` (CIP-129 or CIP-105) or `drep_script1...` identifier
+/// into its 29 bytes CIP-129 id.
+#[pg_extern(immutable)]
+fn drep_from_bech32(drep: &str) -> Option<Vec<u8>> {
+    DREP_ID
+        .parse_bech32(drep)
+        .map_or_else(|err| on_invalid_argument(err, vec![]), Some)
+}
+
+#[pg_extern(immutable)]
+fn cc_hot_to_bech32(cc_hot_id: &[u8]) -> Option<String> {
+    CC_HOT_ID
+        .to_bech32(cc_hot_id)
+        .map_or_else(|err| on_invalid_argument(err, String::new()), Some)
+}
+
+#[pg_extern(immutable, name = "cc_hot_to_bech32")]
+fn cc_hot_credential_to_bech32(credential: &[u8], is_script: bool) -> Option<String> {
+    CC_HOT_ID
+        .credential_id(credential, is_script)
+        .and_then(|x| CC_HOT_ID.to_bech32(&x))
+        .map_or_else(|err| on_invalid_argument(err, String::new()), Some)
+}
+
+#[pg_extern(immutable)]
+fn cc_hot_from_bech32(cc_hot: &str) -> Option<Vec<u8>> {
+    CC_HOT_ID
+        .parse_bech32(cc_hot)
+        .map_or_else(|err| on_invalid_argument(err, vec![]), Some)
+}
+
+#[pg_extern(immutable)]
+fn cc_cold_to_bech32(cc_cold_id: &[u8]) -> Option<String> {
+    CC_COLD_ID
+        .to_bech32(cc_cold_id)
+        .map_or_else(|err| on_invalid_argument(err, String::new()), Some)
+}
+
+#[pg_extern(immutable, name = "cc_cold_to_bech32")]
+fn cc_cold_credential_to_bech32(credential: &[u8], is_script: bool) -> Option<String> {
+    CC_COLD_ID
+        .credential_id(credential, is_script)
+        .and_then(|x| CC_COLD_ID.to_bech32(&x))
+        .map_or_else(|err| on_invalid_argument(err, String::new()), Some)
+}
+
+#[pg_extern(immutable)]
+fn cc_cold_from_bech32(cc_cold: &str) -> Option<Vec<u8>> {
+    CC_COLD_ID
+        .parse_bech32(cc_cold)
+        .map_or_else(|err| on_invalid_argument(err, vec![]), Some)
+}
+
+#[pg_extern(immutable)]
+fn pool_id_to_bech32(pool_id: &[u8]) -> Option<String> {
+    if pool_id.len() != 28 {
+        return on_invalid_argument(
+            format!("invalid pool id: {}", hex::encode(pool_id)),
+            String::new(),
+        );
+    }
+
+    to_bech32(pool_id, "pool")
+}
+
+#[pg_extern(immutable)]
+fn pool_id_from_bech32(pool: &str) -> Option<Vec<u8>> {
+    match bech32::decode(pool) {
+        Ok((hrp, data, _)) if hrp == "pool" => match Vec::from_base32(&data) {
+            Ok(x) if x.len() == 28 => Some(x),
+            _ => on_invalid_argument(format!("invalid pool id: {}", pool), vec![]),
+        },
+        _ => on_invalid_argument(format!("invalid pool id: {}", pool), vec![]),
+    }
+}
+
+#[pg_extern(immutable)]
+fn gov_action_id_to_bech32(tx_hash: &[u8], index: i32) -> Option<String> {
+    match u8::try_from(index) {
+        Ok(index) if tx_hash.len() == 32 => Some(encode_gov_action_id(tx_hash, index)),
+        _ => on_invalid_argument(
+            format!("invalid gov action id: {}#{}", hex::encode(tx_hash), index),
+            String::new(),
+        ),
+    }
+}
+
+/// Decodes a CIP-129 `gov_action1...` identifier.
+///
+/// # Arguments
+///
+/// * `gov_action` - The bech32 identifier.
+///
+/// # Returns
+///
+/// A single row with the hash of the transaction that submitted the proposal
+/// and the index of the proposal in it.
+///
+/// # Example
+///
+/// ```
+/// select * from gov_action_id_from_bech32('gov_action1...');
+/// ```
+#[pg_extern(immutable)]
+fn gov_action_id_from_bech32(
+    gov_action: &str,
+) -> Option<TableIterator<'static, (name!(tx_hash, Vec<u8>), name!(proposal_index, i32))>> {
+    let bytes = match bech32::decode(gov_action) {
+        Ok((hrp, data, _)) if hrp == "gov_action" => Vec::from_base32(&data).ok(),
+        _ => None,
+    };
+
+    match bytes {
+        Some(x) if x.len() == 33 => {
+            let index = i32::from(x[32]);
+            Some(TableIterator::once((x[..32].to_vec(), index)))
+        }
+        _ => on_invalid_argument(
+            format!("invalid gov action id: {}", gov_action),
+            TableIterator::new(std::iter::empty()),
+        ),
+    }
+}
+
+/// Computes the CIP-14 fingerprint of an asset.
+///
+/// # Arguments
+///
+/// * `policy_id` - The 28 bytes policy id.
+/// * `asset_name` - The asset name, up to 32 bytes.
+///
+/// # Returns
+///
+/// The `asset1...` fingerprint.
+///
+/// # Example
+///
+/// ```
+/// select asset_fingerprint(policy_id, asset_name) from utxo_asset_values(era, body);
+/// ```
+#[pg_extern(immutable)]
+fn asset_fingerprint(policy_id: &[u8], asset_name: &[u8]) -> Option<String> {
+    match asset_subject::from_parts(policy_id, asset_name) {
+        Ok(x) => Some(encode_asset_fingerprint(&x.fingerprint)),
+        Err(err) => on_invalid_argument(err, String::new()),
+    }
+}
+
+#[pg_extern(immutable)]
+fn utxo_address(era: i32, utxo_cbor: &[u8]) -> Option<Vec<u8>> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, None).flatten(),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+    let output = output.get();
+
+    output.address().ok().map(|address| address.to_vec())
+}
+
+#[pg_extern(immutable)]
+fn utxo_has_policy_id(era: i32, utxo_cbor: &[u8], policy_id: &[u8]) -> Option<bool> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let output = output.get();
+
+    Some(
+        output
+            .value()
+            .assets()
+            .to_vec()
+            .iter()
+            .any(|a| a.policy().deref().eq(&policy_id)),
+    )
+}
+
+#[pg_extern(immutable)]
+fn utxo_has_address(era: i32, utxo_cbor: &[u8], address: &[u8]) -> Option<bool> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, false),
+    };
+    let output = output.get();
+
+    Some(
+        output
+            .address()
+            .ok()
+            .map(|iter_address| iter_address.to_vec().eq(&address))
+            .unwrap_or_else(|| false),
+    )
+}
+
+#[pg_extern(immutable)]
+fn utxo_lovelace(era: i32, utxo_cbor: &[u8]) -> Option<pgrx::AnyNumeric> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+    let output = output.get();
+
+    Some(AnyNumeric::from(output.value().coin()))
+}
+
+/// Returns the value (lovelace and native assets) held by a UTxO.
+///
+/// # Arguments
+///
+/// * `era` - The era the UTxO was produced in.
+/// * `utxo_cbor` - The CBOR of the transaction output.
+///
+/// # Returns
+///
+/// The `cardano_value` of the output.
+///
+/// # Example
+///
+/// ```
+/// select sum(utxo_value(era, body)) from utxos where utxo_has_address(era, body, $1);
+/// ```
+#[pg_extern(immutable)]
+fn utxo_value(era: i32, utxo_cbor: &[u8]) -> Option<cardano_value> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, ValueAmounts::default().into()),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, ValueAmounts::default().into()),
+    };
+
+    Some(ValueAmounts::from_output(output.get()).into())
+}
+
+#[pg_extern(immutable)]
+fn utxo_policy_id_asset_names(
+    era: i32,
+    utxo_cbor: &[u8],
+    policy_id: &[u8],
+) -> Option<SetOfIterator<'static, Vec<u8>>> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, SetOfIterator::new(std::iter::empty())),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, SetOfIterator::new(std::iter::empty())),
+    };
+    let output = output.get();
+
+    let asset_names = output
+        .value()
+        .assets()
+        .to_vec()
+        .iter()
+        .filter(|a| a.policy().deref().eq(&policy_id))
+        .flat_map(|a| {
+            a.assets()
+                .iter()
+                .map(|a| a.name().to_vec())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    Some(SetOfIterator::new(asset_names))
+}
+
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable)]
+fn utxo_asset_values(
+    era: i32,
+    utxo_cbor: &[u8],
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(policy_id, Vec<u8>),
+            name!(asset_name, Vec<u8>),
+            name!(amount, pgrx::AnyNumeric),
+            name!(fingerprint, String),
+        ),
+    >,
+> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let output = output.get();
+
+    let asset_values = output
+        .value()
+        .assets()
+        .to_vec()
+        .iter()
+        .flat_map(|a| {
+            a.assets()
+                .iter()
+                .map(|a| {
+                    (
+                        a.policy().to_vec(),
+                        a.name().to_vec(),
+                        AnyNumeric::from(a.any_coin()),
+                        encode_asset_fingerprint(&asset_fingerprint_hash(
+                            a.policy().as_ref(),
+                            a.name(),
+                        )),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(asset_values))
+}
+
+#[pg_extern(immutable)]
+fn utxo_policy_id_asset_values(
+    era: i32,
+    utxo_cbor: &[u8],
+    policy_id: &[u8],
+) -> Option<TableIterator<'static, (name!(asset_name, Vec<u8>), name!(amount, pgrx::AnyNumeric))>> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, TableIterator::new(std::iter::empty())),
+    };
+    let output = output.get();
+
+    let asset_values = output
+        .value()
+        .assets()
+        .to_vec()
+        .iter()
+        .filter(|a| a.policy().deref().eq(&policy_id))
+        .flat_map(|a| {
+            a.assets()
+                .iter()
+                .map(|a| (a.name().to_vec(), AnyNumeric::from(a.any_coin())))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    Some(TableIterator::new(asset_values))
+}
+
+#[pg_extern(immutable)]
+fn utxo_subject_amount(era: i32, utxo_cbor: &[u8], subject: &[u8]) -> Option<pgrx::AnyNumeric> {
+    match asset_subject::from_bytes(subject) {
+        Ok(x) => utxo_asset_subject_amount(era, utxo_cbor, x),
+        Err(err) => on_invalid_argument(err, AnyNumeric::from(0)),
+    }
+}
+
+/// Like `utxo_subject_amount`, but takes an `asset_subject`.
+#[pg_extern(immutable)]
+fn utxo_asset_subject_amount(
+    era: i32,
+    utxo_cbor: &[u8],
+    subject: asset_subject,
+) -> Option<pgrx::AnyNumeric> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, AnyNumeric::from(0)),
+    };
+
+    Some(AnyNumeric::from(subject.output_amount(output.get())))
+}
+
+#[pg_extern(immutable)]
+fn utxo_plutus_data(era: i32, utxo_cbor: &[u8]) -> Option<pgrx::Json> {
+    let era_enum = match pallas::ledger::traverse::Era::try_from(era as u16) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, None).flatten(),
+    };
+
+    let output = match decode_utxo(era_enum, utxo_cbor) {
+        Ok(x) => x,
+        Err(err) => return on_decode_error(err, None).flatten(),
+    };
+    let output = output.get();
+
+    output.datum().and_then(|datum_option| match datum_option {
+        pallas::ledger::primitives::conway::PseudoDatumOption::Hash(_) => None,
+        pallas::ledger::primitives::conway::PseudoDatumOption::Data(d) => {
+            Some(pgrx::Json(d.deref().to_json()))
+        }
+    })
+}
+
+#[pg_extern(immutable, name = "utxo_address")]
+fn cardano_utxo_address(utxo: cardano_utxo) -> Option<Vec<u8>> {
+    utxo_address(utxo.era.into(), &utxo.cbor)
+}
+
+#[pg_extern(immutable, name = "utxo_has_policy_id")]
+fn cardano_utxo_has_policy_id(utxo: cardano_utxo, policy_id: &[u8]) -> Option<bool> {
+    utxo_has_policy_id(utxo.era.into(), &utxo.cbor, policy_id)
+}
+
+#[pg_extern(immutable, name = "utxo_has_address")]
+fn cardano_utxo_has_address(utxo: cardano_utxo, address: &[u8]) -> Option<bool> {
+    utxo_has_address(utxo.era.into(), &utxo.cbor, address)
+}
+
+#[pg_extern(immutable, name = "utxo_lovelace")]
+fn cardano_utxo_lovelace(utxo: cardano_utxo) -> Option<pgrx::AnyNumeric> {
+    utxo_lovelace(utxo.era.into(), &utxo.cbor)
+}
+
+#[pg_extern(immutable, name = "utxo_value")]
+fn cardano_utxo_value(utxo: cardano_utxo) -> Option<cardano_value> {
+    utxo_value(utxo.era.into(), &utxo.cbor)
+}
+
+#[pg_extern(immutable, name = "utxo_policy_id_asset_names")]
+fn cardano_utxo_policy_id_asset_names(
+    utxo: cardano_utxo,
+    policy_id: &[u8],
+) -> Option<SetOfIterator<'static, Vec<u8>>> {
+    utxo_policy_id_asset_names(utxo.era.into(), &utxo.cbor, policy_id)
+}
+
+#[allow(clippy::type_complexity)]
+#[pg_extern(immutable, name = "utxo_asset_values")]
+fn cardano_utxo_asset_values(
+    utxo: cardano_utxo,
+) -> Option<
+    TableIterator<
+        'static,
+        (
+            name!(policy_id, Vec<u8>),
+            name!(asset_name, Vec<u8>),
+            name!(amount, pgrx::AnyNumeric),
+            name!(fingerprint, String),
+        ),
+    >,
+> {
+    utxo_asset_values(utxo.era.into(), &utxo.cbor)
+}
+
+#[pg_extern(immutable, name = "utxo_policy_id_asset_values")]
+fn cardano_utxo_policy_id_asset_values(
+    utxo: cardano_utxo,
+    policy_id: &[u8],
+) -> Option<TableIterator<'static, (name!(asset_name, Vec<u8>), name!(amount, pgrx::AnyNumeric))>> {
+    utxo_policy_id_asset_values(utxo.era.into(), &utxo.cbor, policy_id)
+}
+
+#[pg_extern(immutable, name = "utxo_subject_amount")]
+fn cardano_utxo_subject_amount(utxo: cardano_utxo, subject: &[u8]) -> Option<pgrx::AnyNumeric> {
+    utxo_subject_amount(utxo.era.into(), &utxo.cbor, subject)
+}
+
+#[pg_extern(immutable, name = "utxo_asset_subject_amount")]
+fn cardano_utxo_asset_subject_amount(
+    utxo: cardano_utxo,
+    subject: asset_subject,
+) -> Option<pgrx::AnyNumeric> {
+    utxo_asset_subject_amount(utxo.era.into(), &utxo.cbor, subject)
+}
+
+#[pg_extern(immutable, name = "utxo_plutus_data")]
+fn cardano_utxo_plutus_data(utxo: cardano_utxo) -> Option<pgrx::Json> {
+    utxo_plutus_data(utxo.era.into(), &utxo.cbor)
+}
+
+#[pg_extern(immutable)]
+fn to_bech32(hash: &[u8], hrp: &str) -> Option<String> {
+    match bech32::encode(hrp, hash.to_base32(), bech32::Variant::Bech32) {
+        Ok(x) => Some(x),
+        Err(err) => on_invalid_argument(format!("invalid bech32 hrp: {}", err), "".to_string()),
+    }
+}
+
+#[pg_extern(immutable)]
+fn from_bech32(bech32: &str) -> Option<Vec<u8>> {
+    let data = match bech32::decode(bech32) {
+        Ok((_, data, _)) => data,
+        Err(err) => return on_decode_error(err, vec![]),
+    };
+
+    match Vec::from_base32(&data) {
+        Ok(x) => Some(x),
+        Err(err) => on_decode_error(err, vec![]),
+    }
+}
+
+fn output_to_address_string(output: &MultiEraOutput) -> Option<String> {
+    match output.address() {
+        Ok(addr) => {
+            let addr_str = addr.to_string();
+            // Truncate the address to 103 characters typical HEADER, PAYMENT PART and DELEGATION PART encoding in bech32
+            if addr_str.len() > 103 {
+                Some(addr_str[..103].to_string())
+            } else {
+                Some(addr_str)
+            }
+        }
+        Err(_) => None,
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use super::*;
+
+    #[pg_test]
+    fn test_hello_extension() {
+        assert_eq!("Hello, extension", crate::hello_extension());
+    }
+
+    // Decoded transaction data for testing
+    const TX_DATA_HEX: &str = "84ad009282582040e50ebf0ded25391f7dd13ad2d32a8eef5a2cc76cc0e95b8bb2330c482def2f0082582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520082582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520282582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520382582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520482582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520582582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520682582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520782582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520882582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520982582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520a82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520b82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520c82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520d82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520e82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520f82582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822521082582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f282252110182a300581d71071bd7f4b5e059ea90e763467cf559167b21c82ef1cb5fe34fb7a9e501821a030a32c0a3581c1cc1aceaf5c7df55e270864a60600b9f52383fe418164574ffdeeed0a14010581cc0e5564cf5786031d9053f567ec78b8383a0f2bc01318e690e0503f4a14001581cf66d78b4a3cb3d37afa0ec36461e51ecbde00f26c8f0a68f94b69880a144695553441b00000201d16e7cf2028201d818479f0000000000ff82583901da299558c70a8970781806dca93d1801ba2f3b3894227a7b284786e49baba19195b7cb8b1c6febb192cc487b5e8b96d737baddb8bb09866f1b000000015053786b021a0007a272031a07138899075820d36a2619a672494604e11bb447cbcf5231e9f2ba25c2169177edc941bd50ad6c081a0713876d0b5820de92cfe211abe2b770d253ff364362e4281c96ce70c3048b104acb5fc172ea900d8182582040e50ebf0ded25391f7dd13ad2d32a8eef5a2cc76cc0e95b8bb2330c482def2f000e81581cda299558c70a8970781806dca93d1801ba2f3b3894227a7b284786e40f011082583901da299558c70a8970781806dca93d1801ba2f3b3894227a7b284786e49baba19195b7cb8b1c6febb192cc487b5e8b96d737baddb8bb09866f1b00000001502d541d111a002dc6c0128482582032536acbfa12b80a3c570b1dac7948187dfa66992460d11542f67ba357c0fd2c0082582083d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f2822520182582089f6715ff7affd8bdeff696f47d7a08bd899cc9c627483a8885f9fd3943286a100825820db7900797bf9c1235976b226d0cdbe1040d199555158bfe2bc042575f142da6100a30082825820f44ce6186d190f8776fd871d753df7ae503972e4793a2360a423d2f96021e60158400b18e4fcf4be17a531d3fd7a0320df6ba7acbff31b35118275d7ff1cb3de25523453c767af39ac1f3b435749c44af64ecbaf19ae1e23a7b4ab9c7939d653a90182582063179f731829d60aade12a1398c07b7a905cc38e7d9901850c9b186946f5ca3e58403b3932c709d9a355f8a0bb453d2722f39f82a16bb7669669f11698cacc825ce2a74b26aa31f3740a8a820829bfda3f6f3f4bbce1f045707d037df0085273a50004800591840001d87980821a001aaf3e1a315977a684000ad87980821a00011efa1a01c4794f840006d87980821a00011efa1a01c4794f840004d87980821a00011efa1a01c4794f840002d87980821a00011efa1a01c4794f840003d87980821a00011efa1a01c4794f840005d87980821a00011efa1a01c4794f840008d87980821a00011efa1a01c4794f840007d87980821a00011efa1a01c4794f840009d87980821a00011efa1a01c4794f84000ed87980821a00011efa1a01c4794f84000cd87980821a00011efa1a01c4794f84000bd87980821a00011efa1a01c4794f84000dd87980821a00011efa1a01c4794f840010d87980821a00011efa1a01c4794f84000fd87980821a00011efa1a01c4794f840011d87980821a00011efa1a01c4794ff5a0";
+
+    #[pg_test]
+    fn test_tx_hash() {
+        // Expected hash result for the given transaction data
+        const EXPECTED_HASH: &str =
+            "691bb954d364ac5a2fe4bafc72b43a77edee54bd4237d748547426f14f304c96";
+
+        let tx_cbor = hex::decode(TX_DATA_HEX).expect("Failed to decode hex string into bytes");
+
+        assert_eq!(
+            EXPECTED_HASH,
+            crate::tx_hash(&tx_cbor).unwrap(),
+            "The hash of the provided transaction data did not match the expected value."
+        );
+    }
+
+    #[pg_test]
+    fn test_utxo_address() {
+        let utxo_hex = "825839000ba2902f70b40716d84de3d9c01ddc19b514d18f9b6911319a72900d6ee29460029464593dd53cd1435025e2e5614f60be06104c54b472eb1a68022c9f";
+        let utxo_address = utxo_address(7, &hex::decode(utxo_hex).unwrap());
+        assert_eq!("000ba2902f70b40716d84de3d9c01ddc19b514d18f9b6911319a72900d6ee29460029464593dd53cd1435025e2e5614f60be06104c54b472eb", utxo_address.map(hex::encode).unwrap());
+    }
+
+    #[pg_test]
+    fn test_decode_cache_reuses_decoded_tx() {
+        let first = hex::decode(TX_DATA_HEX).unwrap();
+        let second = first.clone();
+
+        assert!(Rc::ptr_eq(
+            &crate::decode_tx(&first).unwrap(),
+            &crate::decode_tx(&second).unwrap()
+        ));
+    }
+
+    #[pg_test]
+    fn test_on_decode_error_null() {
+        Spi::run("SET mumak.on_decode_error = 'null'").unwrap();
+
+        assert_eq!(None, crate::tx_fee(&[0xde, 0xad, 0xbe, 0xef]));
+    }
+
+    #[pg_test]
+    fn test_on_decode_error_sentinel() {
+        Spi::run("SET mumak.on_decode_error = 'sentinel'").unwrap();
+
+        assert_eq!(
+            Some(AnyNumeric::from(0)),
+            crate::tx_fee(&[0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(
+            Some(AnyNumeric::from(-1)),
+            crate::tx_treasury_donation(&[0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(
+            Some(AnyNumeric::from(-1)),
+            crate::tx_current_treasury_value(&[0xde, 0xad, 0xbe, 0xef])
+        );
+    }
+
+    #[pg_test(error = "failed to decode input: Unknown CBOR structure: deadbeef")]
+    fn test_on_decode_error_error() {
+        Spi::run("SET mumak.on_decode_error = 'error'").unwrap();
+
+        crate::tx_hash(&[0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[pg_test]
+    fn test_tx_decode_error() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        assert!(crate::tx_decode_error(&tx_cbor).is_none());
+
+        let truncated = &tx_cbor[..200];
+        let diagnostic = crate::tx_decode_diagnostic(truncated).unwrap();
+        assert_eq!(Some(200), diagnostic.offset);
+        assert_eq!(Some(pallas::ledger::traverse::Era::Conway), diagnostic.era);
+    }
+
+    #[pg_test]
+    fn test_block_decode_error() {
+        // a conway tag followed by an empty block
+        let diagnostic = crate::block_decode_diagnostic(&[0x82, 0x07, 0x80]).unwrap();
+        assert_eq!(Some(2), diagnostic.offset);
+        assert_eq!(Some(pallas::ledger::traverse::Era::Conway), diagnostic.era);
+
+        assert!(crate::block_decode_error(&[0xde, 0xad, 0xbe, 0xef]).is_some());
+    }
+
+    // A conway transaction with a single `reg` certificate for key hash 0x11..11
+    const TX_REG_CERT_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a00029810048183078200581c111111111111111111111111111111111111111111111111111111111a001e8480a0f5f6";
+
+    #[pg_test]
+    fn test_tx_certificates() {
+        let tx_cbor = hex::decode(TX_REG_CERT_HEX).unwrap();
+
+        let certificates: Vec<_> = crate::tx_certificates(&tx_cbor).unwrap().collect();
+        assert_eq!(1, certificates.len());
+
+        let (index, kind, credential, credential_is_script, pool_id, deposit) = &certificates[0];
+        assert_eq!(0, *index);
+        assert_eq!("reg", kind);
+        assert_eq!(Some(vec![0x11; 28]), *credential);
+        assert_eq!(Some(false), *credential_is_script);
+        assert_eq!(None, *pool_id);
+        assert_eq!(Some(AnyNumeric::from(2000000u64)), *deposit);
+
+        assert_eq!(
+            serde_json::json!([{
+                "index": 0,
+                "kind": "reg",
+                "credential": "11".repeat(28),
+                "credential_is_script": false,
+                "pool_id": null,
+                "deposit": "2000000",
+            }]),
+            crate::tx_certificates_json(&tx_cbor).unwrap().0
+        );
+
+        assert_eq!(Some(true), crate::tx_has_certificate_kind(&tx_cbor, "reg"));
+        assert_eq!(
+            Some(false),
+            crate::tx_has_certificate_kind(&tx_cbor, "pool_retirement")
+        );
+    }
+
+    // TX_REG_CERT_HEX with post-alonzo auxiliary data holding labels 1 (bytes),
+    // 2 (an int below i64::MIN), 3 (a map with an int key) and 674
+    const TX_METADATA_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a00029810048183078200581c111111111111111111111111111111111111111111111111111111111a001e8480a0f5d90103a100a41902a2a1636d7367816268690141ff023bffffffffffffffff03a1016161";
+
+    #[pg_test]
+    fn test_tx_metadata() {
+        let tx_cbor = hex::decode(TX_METADATA_HEX).unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "1": "0xff",
+                "2": "-18446744073709551616",
+                "3": { "1": "a" },
+                "674": { "msg": ["hi"] },
+            }),
+            crate::tx_metadata(&tx_cbor).unwrap().0
+        );
+        assert_eq!(
+            serde_json::json!({ "msg": ["hi"] }),
+            crate::tx_metadata_label(&tx_cbor, 674).unwrap().0
+        );
+        assert!(crate::tx_metadata_label(&tx_cbor, 675).is_none());
+
+        assert_eq!(Some(true), crate::tx_has_metadata_label(&tx_cbor, 674));
+        assert_eq!(Some(false), crate::tx_has_metadata_label(&tx_cbor, 675));
+
+        let tx_cbor = hex::decode(TX_REG_CERT_HEX).unwrap();
+        assert!(crate::tx_metadata(&tx_cbor).is_none());
+        assert_eq!(Some(false), crate::tx_has_metadata_label(&tx_cbor, 674));
+    }
+
+    // TX_REG_CERT_HEX with auxiliary data cut short after an empty metadata map
+    const TX_TRUNCATED_METADATA_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a00029810048183078200581c111111111111111111111111111111111111111111111111111111111a001e8480a0f5d90103a200a00181";
+
+    #[pg_test(
+        error = "failed to decode input: Unknown CBOR structure: 84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a00029810048183078200581c111111111111111111111111111111111111111111111111111111111a001e8480a0f5d90103a200a00181"
+    )]
+    fn test_tx_has_metadata_label_truncated() {
+        Spi::run("SET mumak.on_decode_error = 'error'").unwrap();
+
+        let tx_cbor = hex::decode(TX_TRUNCATED_METADATA_HEX).unwrap();
+        crate::tx_has_metadata_label(&tx_cbor, 674);
+    }
+
+    // a transaction minting "Nft1" and "Nft2" under policy 2222...22 with
+    // CIP-25 metadata for "Nft1" only: the v1 layout keys it by hex policy id
+    // and utf8 asset name, the v2 layout by raw bytes
+    const TX_CIP25_V1_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a0002981009a1581c22222222222222222222222222222222222222222222222222222222a2444e66743101444e66743201a0f5a11902d1a278383232323232323232323232323232323232323232323232323232323232323232323232323232323232323232323232323232323232323232a1644e667431a4646e616d65654e4654203165696d6167658269697066733a2f2f516d63616263696d656469615479706569696d6167652f706e676566696c657381a2696d656469615479706569696d6167652f706e67637372636c697066733a2f2f516d6162636776657273696f6e63312e30";
+    const TX_CIP25_V2_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a0002981009a1581c22222222222222222222222222222222222222222222222222222222a2444e66743101444e66743201a0f5a11902d1a2581c22222222222222222222222222222222222222222222222222222222a1444e667431a4646e616d65654e4654203165696d6167658269697066733a2f2f516d63616263696d656469615479706569696d6167652f706e676566696c657381a2696d656469615479706569696d6167652f706e67637372636c697066733a2f2f516d6162636776657273696f6e02";
+
+    // TX_CIP25_V1_HEX with the asset name keyed as hex text
+    const TX_CIP25_V1_HEX_NAME_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a0002981009a1581c22222222222222222222222222222222222222222222222222222222a2444e66743101444e66743201a0f5a11902d1a278383232323232323232323232323232323232323232323232323232323232323232323232323232323232323232323232323232323232323232a1683465363637343331a4646e616d65654e4654203165696d6167658269697066733a2f2f516d63616263696d656469615479706569696d6167652f706e676566696c657381a2696d656469615479706569696d6167652f706e67637372636c697066733a2f2f516d6162636776657273696f6e63312e30";
+    // TX_CIP25_V2_HEX burning "Nft1" instead of minting it
+    const TX_CIP25_BURN_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a0002981009a1581c22222222222222222222222222222222222222222222222222222222a2444e66743120444e66743201a0f5a11902d1a2581c22222222222222222222222222222222222222222222222222222222a1444e667431a4646e616d65654e4654203165696d6167658269697066733a2f2f516d63616263696d656469615479706569696d6167652f706e676566696c657381a2696d656469615479706569696d6167652f706e67637372636c697066733a2f2f516d6162636776657273696f6e02";
+
+    #[pg_test]
+    fn test_tx_mint_cip25() {
+        for tx_hex in [TX_CIP25_V1_HEX, TX_CIP25_V1_HEX_NAME_HEX, TX_CIP25_V2_HEX] {
+            let tx_cbor = hex::decode(tx_hex).unwrap();
+
+            let rows: Vec<_> = crate::tx_mint_cip25(&tx_cbor).unwrap().collect();
+            assert_eq!(1, rows.len());
+
+            let (policy_id, asset_name, name, image, media_type, files, metadata) = &rows[0];
+            assert_eq!(&vec![0x22; 28], policy_id);
+            assert_eq!(b"Nft1", asset_name.as_slice());
+            assert_eq!(Some("NFT 1"), name.as_deref());
+            assert_eq!(Some("ipfs://Qmabc"), image.as_deref());
+            assert_eq!(Some("image/png"), media_type.as_deref());
+            assert_eq!(
+                serde_json::json!([{ "mediaType": "image/png", "src": "ipfs://Qmabc" }]),
+                files.as_ref().unwrap().0
+            );
+            assert_eq!(serde_json::json!(["ipfs://Qm", "abc"]), metadata.0["image"]);
+
+            assert_eq!(Some(true), crate::tx_has_mint_cip25(&tx_cbor));
+        }
+
+        // metadata is only reported for minted assets
+        let tx_cbor = hex::decode(TX_CIP25_BURN_HEX).unwrap();
+        assert_eq!(0, crate::tx_mint_cip25(&tx_cbor).unwrap().count());
+        assert_eq!(Some(false), crate::tx_has_mint_cip25(&tx_cbor));
+
+        // metadata without label 721
+        let tx_cbor = hex::decode(TX_METADATA_HEX).unwrap();
+        assert_eq!(0, crate::tx_mint_cip25(&tx_cbor).unwrap().count());
+        assert_eq!(Some(false), crate::tx_has_mint_cip25(&tx_cbor));
+
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        assert_eq!(Some(false), crate::tx_has_mint_cip25(&tx_cbor));
+    }
+
+    #[pg_test(
+        error = "failed to decode input: Unknown CBOR structure: 84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a00029810048183078200581c111111111111111111111111111111111111111111111111111111111a001e8480a0f5d90103a200a00181"
+    )]
+    fn test_tx_has_mint_cip25_truncated() {
+        Spi::run("SET mumak.on_decode_error = 'error'").unwrap();
+
+        let tx_cbor = hex::decode(TX_TRUNCATED_METADATA_HEX).unwrap();
+        crate::tx_has_mint_cip25(&tx_cbor);
+    }
+
+    #[pg_test]
+    fn test_tx_validity() {
+        // validity start 0x0713876d, ttl 0x07138899
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+
+        assert_eq!(Some(118720365), crate::tx_validity_start(&tx_cbor));
+        assert_eq!(Some(118720665), crate::tx_validity_end(&tx_cbor));
+        assert_eq!(
+            Some(pgrx::Range::new(
+                RangeBound::Inclusive(118720365),
+                RangeBound::Exclusive(118720665)
+            )),
+            crate::tx_validity_range(&tx_cbor, None)
+        );
+
+        assert_eq!(
+            Some(false),
+            crate::tx_is_valid_at_slot(&tx_cbor, 118720364, None)
+        );
+        assert_eq!(
+            Some(true),
+            crate::tx_is_valid_at_slot(&tx_cbor, 118720365, None)
+        );
+        assert_eq!(
+            Some(false),
+            crate::tx_is_valid_at_slot(&tx_cbor, 118720665, None)
+        );
+
+        // the shelley TTL is the last valid slot
+        assert_eq!(
+            Some(true),
+            crate::tx_is_valid_at_slot(&tx_cbor, 118720665, Some(1))
+        );
+        assert_eq!(
+            Some(false),
+            crate::tx_is_valid_at_slot(&tx_cbor, 118720666, Some(1))
+        );
+        assert_eq!(
+            Some(pgrx::Range::new(
+                RangeBound::Inclusive(118720365),
+                RangeBound::Exclusive(118720666)
+            )),
+            crate::tx_validity_range(&tx_cbor, Some(1))
+        );
+
+        let tx_cbor = hex::decode(TX_REG_CERT_HEX).unwrap();
+        assert_eq!(None, crate::tx_validity_end(&tx_cbor));
+        assert_eq!(Some(true), crate::tx_is_valid_at_slot(&tx_cbor, 0, None));
+    }
+
+    #[pg_test]
+    fn test_tx_collateral() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+
+        assert_eq!(
+            Some(vec![Some(
+                "40e50ebf0ded25391f7dd13ad2d32a8eef5a2cc76cc0e95b8bb2330c482def2f#0".to_string()
+            )]),
+            crate::tx_collateral_inputs(&tx_cbor)
+        );
+        assert_eq!(
+            Some(AnyNumeric::from(3000000u64)),
+            crate::tx_total_collateral(&tx_cbor)
+        );
+
+        let collateral_return: Vec<_> = crate::tx_collateral_return(&tx_cbor).unwrap().collect();
+        assert_eq!(1, collateral_return.len());
+        assert_eq!(2, collateral_return[0].0);
+        assert_eq!(AnyNumeric::from(5640115229u64), collateral_return[0].2);
+    }
+
+    #[pg_test]
+    fn test_tx_outputs_of_invalid_tx() {
+        // TX_DATA_HEX flagged as having failed phase-2 validation
+        let tx_hex = format!("{}f4a0", &TX_DATA_HEX[..TX_DATA_HEX.len() - 4]);
+        let tx_cbor = hex::decode(tx_hex).unwrap();
+
+        let produced: Vec<_> = crate::tx_outputs(&tx_cbor, true).unwrap().collect();
+        assert_eq!(1, produced.len());
+        assert_eq!(2, produced[0].0);
+
+        let declared: Vec<_> = crate::tx_outputs(&tx_cbor, false).unwrap().collect();
+        assert_eq!(2, declared.len());
+
+        assert_eq!(
+            Some(AnyNumeric::from(5640115229u64)),
+            crate::tx_lovelace(&tx_cbor, true)
+        );
+        assert_eq!(
+            Some(AnyNumeric::from(5688703787u64)),
+            crate::tx_lovelace(&tx_cbor, false)
+        );
+
+        // the collateral input is spent instead of the 18 inputs of the body
+        let collateral =
+            hex::decode("40e50ebf0ded25391f7dd13ad2d32a8eef5a2cc76cc0e95b8bb2330c482def2f")
+                .unwrap();
+        let consumed: Vec<_> = crate::tx_inputs_table(&tx_cbor, true).unwrap().collect();
+        assert_eq!(vec![(collateral.clone(), 0)], consumed);
+        assert_eq!(18, crate::tx_inputs_table(&tx_cbor, false).unwrap().count());
+        assert_eq!(
+            Some(true),
+            crate::tx_has_input(&tx_cbor, &collateral, 0, true)
+        );
+        assert_eq!(
+            Some(false),
+            crate::tx_has_input(&tx_cbor, &collateral, 0, false)
+        );
+    }
+
+    #[pg_test]
+    fn test_tx_inputs_table() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        let hash = hex::decode("83d52903a465b2cf0dbb0900c1d8a1e2dec10578075cd7484b869a205f282252")
+            .unwrap();
+
+        let inputs: Vec<_> = crate::tx_inputs_table(&tx_cbor, true).unwrap().collect();
+        assert_eq!(18, inputs.len());
+        assert!(inputs.contains(&(hash.clone(), 17)));
+
+        assert_eq!(Some(true), crate::tx_has_input(&tx_cbor, &hash, 17, true));
+        assert_eq!(Some(false), crate::tx_has_input(&tx_cbor, &hash, 18, true));
+        assert_eq!(Some(false), crate::tx_has_input(&tx_cbor, &hash, -1, true));
+        assert_eq!(
+            Some(true),
+            crate::tx_has_reference_input(&tx_cbor, &hash, 1)
+        );
+        assert_eq!(
+            Some(false),
+            crate::tx_has_reference_input(&tx_cbor, &hash, 17)
+        );
+        assert_eq!(
+            Some(4),
+            crate::tx_reference_inputs(&tx_cbor).map(|x| x.len())
+        );
+    }
+
+    #[pg_test]
+    fn test_tx_redeemers() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+
+        let redeemers: Vec<_> = crate::tx_redeemers(&tx_cbor).unwrap().collect();
+        assert_eq!(17, redeemers.len());
+
+        let (tag, index, _, data_cbor, mem, steps) = &redeemers[0];
+        assert_eq!("spend", tag);
+        assert_eq!(1, *index);
+        assert_eq!(&hex::decode("d87980").unwrap(), data_cbor);
+        assert_eq!(1748798, *mem);
+        assert_eq!(827946918, *steps);
+
+        assert_eq!(
+            Some(1748798 + 16 * 73466),
+            crate::tx_total_ex_units_mem(&tx_cbor)
+        );
+        assert_eq!(
+            Some(827946918 + 16 * 29653327),
+            crate::tx_total_ex_units_steps(&tx_cbor)
+        );
+    }
+
+    // TX_REG_CERT_HEX with a spend redeemer whose data, the integer 0, is
+    // encoded with a needless extra byte
+    const TX_REDEEMER_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a00029810048183078200581c111111111111111111111111111111111111111111111111111111111a001e8480a105818400001800820102f5f6";
+
+    #[pg_test]
+    fn test_tx_redeemers_keep_data_encoding() {
+        let tx_cbor = hex::decode(TX_REDEEMER_HEX).unwrap();
+
+        let redeemers: Vec<_> = crate::tx_redeemers(&tx_cbor).unwrap().collect();
+        assert_eq!(1, redeemers.len());
+
+        let (tag, index, data, data_cbor, ..) = &redeemers[0];
+        assert_eq!("spend", tag);
+        assert_eq!(0, *index);
+        assert_eq!(serde_json::json!({ "int": 0 }), data.0);
+        assert_eq!(&vec![0x18, 0x00], data_cbor);
+    }
+
+    #[pg_test]
+    fn test_tx_vkey_witnesses() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+
+        let witnesses: Vec<_> = crate::tx_vkey_witnesses(&tx_cbor).unwrap().collect();
+        assert_eq!(2, witnesses.len());
+        // the first witness belongs to the transaction's required signer
+        assert_eq!(
+            "da299558c70a8970781806dca93d1801ba2f3b3894227a7b284786e4",
+            hex::encode(&witnesses[0].1)
+        );
+    }
+
+    // An unsigned byron transaction spending output 0 of transaction 00..00
+    const TX_BYRON_HEX: &str = "8283818200d8185824825820000000000000000000000000000000000000000000000000000000000000000000818282d818582183581cba970ad36654d8dd8f74274b733452ddeab9a62a397746be3c42ccdda0001a9026da5b1a000f4240a080";
+
+    #[pg_test]
+    fn test_tx_verify_signatures() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+
+        let signatures: Vec<_> = crate::tx_verify_signatures(&tx_cbor).unwrap().collect();
+        assert_eq!(2, signatures.len());
+        assert!(signatures
+            .iter()
+            .all(|(kind, _, _, valid)| kind == "vkey" && *valid));
+        assert_eq!(Some(true), crate::tx_has_valid_signatures(&tx_cbor));
+
+        // tamper with the first byte of the first signature
+        let tx_cbor = hex::decode(TX_DATA_HEX.replacen("58400b18e4", "58400c18e4", 1)).unwrap();
+
+        let signatures: Vec<_> = crate::tx_verify_signatures(&tx_cbor).unwrap().collect();
+        assert!(!signatures[0].3);
+        assert!(signatures[1].3);
+        assert_eq!(Some(false), crate::tx_has_valid_signatures(&tx_cbor));
+
+        // nothing to verify
+        let tx_cbor = hex::decode(TX_REG_CERT_HEX).unwrap();
+        assert_eq!(0, crate::tx_verify_signatures(&tx_cbor).unwrap().count());
+        assert_eq!(None, crate::tx_has_valid_signatures(&tx_cbor));
+
+        let tx_cbor = hex::decode(TX_BYRON_HEX).unwrap();
+        assert_eq!(0, crate::tx_verify_signatures(&tx_cbor).unwrap().count());
+        assert_eq!(None, crate::tx_has_valid_signatures(&tx_cbor));
+    }
+
+    #[pg_test]
+    fn test_tx_signers() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        let required =
+            hex::decode("da299558c70a8970781806dca93d1801ba2f3b3894227a7b284786e4").unwrap();
+        let witness =
+            hex::decode("9baba19195b7cb8b1c6febb192cc487b5e8b96d737baddb8bb09866f").unwrap();
+
+        assert_eq!(
+            Some(vec![required.clone()]),
+            crate::tx_required_signers(&tx_cbor)
+        );
+        assert_eq!(
+            Some(true),
+            crate::tx_has_required_signer(&tx_cbor, &required)
+        );
+        assert_eq!(
+            Some(false),
+            crate::tx_has_required_signer(&tx_cbor, &witness)
+        );
+        assert_eq!(
+            Some(vec![required, witness]),
+            crate::tx_signer_key_hashes(&tx_cbor)
+        );
+    }
+
+    // A conway transaction with a yes vote from drep key hash 0x11..11 on
+    // governance action 0x22..22#0, anchored at https://x with hash 0x33..33
+    const TX_VOTE_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a0002981013a18202581c11111111111111111111111111111111111111111111111111111111a18258202222222222222222222222222222222222222222222222222222222222222222008201826968747470733a2f2f7858203333333333333333333333333333333333333333333333333333333333333333a0f5f6";
+
+    #[pg_test]
+    fn test_tx_votes() {
+        let tx_cbor = hex::decode(TX_VOTE_HEX).unwrap();
+
+        let votes: Vec<_> = crate::tx_votes(&tx_cbor).unwrap().collect();
+        assert_eq!(
+            vec![(
+                "drep_key".to_string(),
+                vec![0x11; 28],
+                vec![0x22; 32],
+                0,
+                "yes".to_string(),
+                Some("https://x".to_string()),
+                Some(vec![0x33; 32]),
+            )],
+            votes
+        );
+
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        assert_eq!(0, crate::tx_votes(&tx_cbor).unwrap().count());
+    }
+
+    // A conway transaction proposing to withdraw 1000 lovelace from the
+    // treasury to reward account e011..11, with a 100k ada deposit
+    const TX_PROPOSAL_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a000298101481841b000000174876e800581de0111111111111111111111111111111111111111111111111111111118302a1581de0111111111111111111111111111111111111111111111111111111111903e8f6826968747470733a2f2f7858203333333333333333333333333333333333333333333333333333333333333333a0f5f6";
+
+    #[pg_test]
+    fn test_tx_proposals() {
+        let tx_cbor = hex::decode(TX_PROPOSAL_HEX).unwrap();
+        let reward_account = format!("e0{}", "11".repeat(28));
+
+        let proposals: Vec<_> = crate::tx_proposals(&tx_cbor).unwrap().collect();
+        assert_eq!(1, proposals.len());
+
+        let (tx_hash, index, id, deposit, account, url, _, action_type, action) = &proposals[0];
+        assert_eq!(
+            "40d8bad614901a9a0b742aec8cd7cad04e0560d954995e3e21524b2b29627d96",
+            hex::encode(tx_hash)
+        );
+        assert_eq!(0, *index);
+        assert_eq!(
+            Some("gov_action1grvt44s5jqdf5zm59tkge4726p8q2cxe2jv4u03p2f9jk2tz0ktqq83s563"),
+            id.as_deref()
+        );
+        assert_eq!(AnyNumeric::from(100000000000u64), *deposit);
+        assert_eq!(reward_account, hex::encode(account));
+        assert_eq!("https://x", url);
+        assert_eq!("treasury_withdrawals", action_type);
+        assert_eq!(
+            serde_json::json!({
+                "withdrawals": { reward_account: "1000" },
+                "policy_hash": null,
+            }),
+            action.0
+        );
+    }
+
+    #[pg_test]
+    fn test_address_info() {
+        // CIP-19 pointer address test vector
+        let pointer =
+            hex::decode("419493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e8198bd431b03")
+                .unwrap();
+        assert_eq!(Some(4), crate::address_type(&pointer));
+
+        let info = crate::address_info(&pointer).unwrap();
+        let field = |name| info.get_by_name::<String>(name).unwrap();
+        assert_eq!(Some("key".to_string()), field("payment_kind"));
+        assert_eq!(Some("pointer".to_string()), field("delegation_kind"));
+
+        let field = |name| info.get_by_name::<i64>(name).unwrap();
+        assert_eq!(Some(2498243), field("pointer_slot"));
+        assert_eq!(Some(27), field("pointer_tx_idx"));
+        assert_eq!(Some(3), field("pointer_cert_idx"));
+        assert_eq!(
+            None,
+            info.get_by_name::<Vec<u8>>("delegation_hash").unwrap()
+        );
+        assert_eq!(Some(1), info.get_by_name::<i32>("network_id").unwrap());
+
+        // CIP-19 testnet byron address test vector
+        let byron = hex::decode("82d818584983581c7e9ee4a9527dea9091e2d580edd6716888c42f75d96276290f98fe0ba201581e581c0cdf39b531d1ac0963cbd183f63e43d895d16a9c567c95e1056e28bd02451a4170cb17001a53249b67").unwrap();
+        assert_eq!(Some(8), crate::address_type(&byron));
+
+        let info = crate::address_info(&byron).unwrap();
+        assert_eq!(None, info.get_by_name::<i32>("network_id").unwrap());
+        assert_eq!(
+            Some("pubkey".to_string()),
+            info.get_by_name::<String>("byron_type").unwrap()
+        );
+        assert_eq!(
+            Some(hex::decode("7e9ee4a9527dea9091e2d580edd6716888c42f75d96276290f98fe0b").unwrap()),
+            info.get_by_name::<Vec<u8>>("byron_root").unwrap()
+        );
+        assert_eq!(
+            Some(1097911063),
+            info.get_by_name::<i64>("byron_protocol_magic").unwrap()
+        );
+        let attributes = info
+            .get_by_name::<pgrx::JsonB>("byron_attributes")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            serde_json::json!("0cdf39b531d1ac0963cbd183f63e43d895d16a9c567c95e1056e28bd"),
+            attributes.0["derivation_path"]
+        );
+    }
+
+    #[pg_test]
+    fn test_address_from_parts() {
+        // CIP-19 test vector credentials
+        let payment =
+            hex::decode("9493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e").unwrap();
+        let stake =
+            hex::decode("337b62cfff6403a06a3acbc34f8c46003c69fe79a3628cefa9c47251").unwrap();
+
+        assert_eq!(
+            "addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x",
+            crate::address_to_bech32(
+                &crate::address_from_parts(1, &payment, false, Some(&stake), false).unwrap()
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            "addr_test1vz2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzerspjrlsz",
+            crate::address_to_bech32(
+                &crate::address_from_parts(0, &payment, false, None, false).unwrap()
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            "stake1uyehkck0lajq8gr28t9uxnuvgcqrc6070x3k9r8048z8y5gh6ffgw",
+            crate::stake_part_to_bech32(
+                &crate::stake_address_from_credential(1, &stake, false).unwrap()
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn test_byron_address_from_string() {
+        let mainnet = "Ae2tdPwUPEZFRbyhz3cpfC2CumGzNkFBN2L42rcUc2yjQpEkxDbkPodpMAi";
+        let bytes = crate::address_from_string(mainnet).unwrap();
+        assert_eq!(
+            "82d818582183581cba970ad36654d8dd8f74274b733452ddeab9a62a397746be3c42ccdda0001a9026da5b",
+            hex::encode(&bytes)
+        );
+        assert_eq!(Some(true), crate::address_is_byron(&bytes));
+        assert_eq!(mainnet, crate::address_to_bech32(&bytes).unwrap());
+        assert_eq!(
+            serde_json::json!({}),
+            crate::byron_address_attributes(&bytes).unwrap().0
+        );
+
+        // CIP-19 testnet byron address test vector
+        let bytes = crate::address_from_string("37btjrVyb4KDXBNC4haBVPCrro8AQPHwvCMp3RFhhSVWwfFmZ6wwzSK6JK1hY6wHNmtrpTf1kdbva8TCneM2YsiXT7mrzT21EacHnPpz5YyUdj64na").unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "derivation_path": "0cdf39b531d1ac0963cbd183f63e43d895d16a9c567c95e1056e28bd",
+                "protocol_magic": 1097911063,
+            }),
+            crate::byron_address_attributes(&bytes).unwrap().0
+        );
+
+        let shelley = crate::address_from_string(
+            "addr_test1vz2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzerspjrlsz",
+        )
+        .unwrap();
+        assert_eq!(Some(false), crate::address_is_byron(&shelley));
+        assert!(crate::byron_address_attributes(&shelley).is_none());
+    }
+
+    #[pg_test]
+    fn test_address_pointer() {
+        // CIP-19 pointer address test vector
+        let pointer =
+            hex::decode("419493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e8198bd431b03")
+                .unwrap();
+        let rows: Vec<_> = crate::address_pointer(&pointer).unwrap().collect();
+        assert_eq!(vec![(2498243, 27, 3)], rows);
+
+        let enterprise =
+            hex::decode("609493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e").unwrap();
+        assert_eq!(0, crate::address_pointer(&enterprise).unwrap().count());
+    }
+
+    // A conway block at slot 100 with the TX_REG_CERT_HEX transaction body
+    const BLOCK_REG_CERT_HEX: &str = "820785828a011864f64040824040005820000000000000000000000000000000000000000000000000000000000000000084400000408209004081a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a00029810048183078200581c111111111111111111111111111111111111111111111111111111111a001e848081a0a080";
+
+    #[pg_test]
+    fn test_resolve_pointer_address() {
+        Spi::run("CREATE TABLE pointer_blocks (slot integer, cbor bytea)").unwrap();
+        Spi::run(&format!(
+            "INSERT INTO pointer_blocks VALUES (100, '\\x{}')",
+            BLOCK_REG_CERT_HEX
+        ))
+        .unwrap();
+
+        let blocks = pgrx::PgRelation::open_with_name_and_share_lock("pointer_blocks").unwrap();
+        // a testnet pointer address to (100, 0, 0)
+        let address =
+            hex::decode("409493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e640000")
+                .unwrap();
+        assert_eq!(
+            vec![(vec![0x11; 28], false)],
+            crate::resolve_pointer_address(&address, blocks, "slot", "cbor")
+                .unwrap()
+                .collect::<Vec<_>>()
+        );
+
+        Spi::run("CREATE VIEW pointer_blocks_view (block_slot, block_cbor) AS SELECT * FROM pointer_blocks")
+            .unwrap();
+        let view = pgrx::PgRelation::open_with_name_and_share_lock("pointer_blocks_view").unwrap();
+        assert_eq!(
+            1,
+            crate::resolve_pointer_address(&address, view, "block_slot", "block_cbor")
+                .unwrap()
+                .count()
+        );
+
+        let block_cbor = hex::decode(BLOCK_REG_CERT_HEX).unwrap();
+        let block = pallas::ledger::traverse::MultiEraBlock::decode(&block_cbor).unwrap();
+        assert_eq!(None, crate::pointer_credential(&block, 0, 1));
+        assert_eq!(None, crate::pointer_credential(&block, 1, 0));
+    }
+
+    #[pg_test]
+    fn test_gov_action_id_to_bech32() {
+        // CIP-129 test vector
+        assert_eq!(
+            "gov_action1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqpzklpgpf",
+            crate::gov_action_id_to_bech32(&[0; 32], 17).unwrap()
+        );
+        assert_eq!(
+            Some((vec![0; 32], 17)),
+            crate::gov_action_id_from_bech32(
+                "gov_action1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqpzklpgpf"
+            )
+            .and_then(|mut x| x.next())
+        );
+
+        // the index is a single byte
+        assert_eq!(
+            Some(String::new()),
+            crate::gov_action_id_to_bech32(&[0; 32], 256)
+        );
+        let long = bech32::encode(
+            "gov_action",
+            bech32::ToBase32::to_base32(&[0u8; 34]),
+            bech32::Variant::Bech32,
+        )
+        .unwrap();
+        assert_eq!(0, crate::gov_action_id_from_bech32(&long).unwrap().count());
+    }
+
+    #[pg_test]
+    fn test_drep_bech32() {
+        let drep_id = [[0x22].as_slice(), &[0x11; 28]].concat();
+        let drep = "drep1ygg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg42v5vz";
+
+        assert_eq!(drep, crate::drep_to_bech32(&drep_id).unwrap());
+        assert_eq!(
+            drep,
+            crate::drep_credential_to_bech32(&[0x11; 28], false).unwrap()
+        );
+        assert_eq!(drep_id, crate::drep_from_bech32(drep).unwrap());
+
+        // CIP-105 script ids are normalized to their CIP-129 bytes
+        assert_eq!(
+            [[0x23].as_slice(), &[0x11; 28]].concat(),
+            crate::drep_from_bech32(
+                "drep_script1zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zez4hcp"
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test(
+        error = "invalid CIP-129 drep id: 1311111111111111111111111111111111111111111111111111111111"
+    )]
+    fn test_drep_to_bech32_invalid_header() {
+        Spi::run("SET mumak.on_decode_error = 'error'").unwrap();
+
+        // a cc_cold header
+        crate::drep_to_bech32(&[[0x13].as_slice(), &[0x11; 28]].concat());
+    }
+
+    #[pg_test]
+    fn test_cc_cold_and_pool_bech32() {
+        assert_eq!(
+            "cc_cold1zvg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3s6n9p",
+            crate::cc_cold_credential_to_bech32(&[0x11; 28], true).unwrap()
+        );
+        assert_eq!(
+            "pool1zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zdqn7r6",
+            crate::pool_id_to_bech32(&[0x11; 28]).unwrap()
+        );
+        assert_eq!(
+            vec![0x11; 28],
+            crate::pool_id_from_bech32("pool1zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zdqn7r6")
+                .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn test_tx_treasury_fields() {
+        // a conway transaction asserting a 50k ada treasury and donating 1 ada
+        let tx_cbor = hex::decode("84a500818258200000000000000000000000000000000000000000000000000000000000000000000180021a00029810151b0000000ba43b7400161a000f4240a0f5f6").unwrap();
+
+        assert_eq!(
+            Some(AnyNumeric::from(1000000u64)),
+            crate::tx_treasury_donation(&tx_cbor)
+        );
+        assert_eq!(
+            Some(AnyNumeric::from(50000000000u64)),
+            crate::tx_current_treasury_value(&tx_cbor)
+        );
+
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        assert_eq!(None, crate::tx_treasury_donation(&tx_cbor));
+        assert_eq!(None, crate::tx_current_treasury_value(&tx_cbor));
+    }
+
+    // TX_REG_CERT_HEX with a native script, a plutus v2 script and a datum in
+    // its witness set
+    const TX_SCRIPTS_HEX: &str = "84a400818258200000000000000000000000000000000000000000000000000000000000000000000180021a00029810048183078200581c111111111111111111111111111111111111111111111111111111111a001e8480a301818200581c11111111111111111111111111111111111111111111111111111111068144010203040481d87980f5f6";
+
+    #[pg_test]
+    fn test_tx_witness_scripts_and_datums() {
+        let tx_cbor = hex::decode(TX_SCRIPTS_HEX).unwrap();
+
+        let native: Vec<_> = crate::tx_native_scripts(&tx_cbor).unwrap().collect();
+        assert_eq!(1, native.len());
+        assert_eq!(
+            "5334c8c55ec6518f2e262da617d3170bf0722f43813d64dbaf0ff0b9",
+            hex::encode(&native[0].0)
+        );
+
+        let plutus: Vec<_> = crate::tx_plutus_scripts(&tx_cbor).unwrap().collect();
+        assert_eq!(1, plutus.len());
+        assert_eq!("plutus_v2", plutus[0].0);
+        assert_eq!(
+            "d3c532b07f1872c3a126fcdb6e866a0eca98f15724b3eeb0c2c35b8e",
+            hex::encode(&plutus[0].1)
+        );
+
+        let datums: Vec<_> = crate::tx_witness_datums(&tx_cbor).unwrap().collect();
+        assert_eq!(1, datums.len());
+        assert_eq!(
+            "923918e403bf43c34b4ef6b48eb2ee04babed17320d8d1b9ff9ad086e86f44ec",
+            hex::encode(&datums[0].0)
+        );
+    }
+
+    #[pg_test]
+    fn test_cardano_tx_cast() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        let tx = crate::bytea_to_cardano_tx(&tx_cbor);
+
+        assert_eq!(tx_cbor, crate::cardano_tx_to_bytea(tx));
+    }
+
+    #[pg_test(error = "invalid cardano_tx: Unknown CBOR structure: deadbeef")]
+    fn test_cardano_tx_rejects_garbage() {
+        crate::bytea_to_cardano_tx(&[0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[pg_test]
+    fn test_cardano_utxo_lovelace() {
+        let utxo_hex = "825839000ba2902f70b40716d84de3d9c01ddc19b514d18f9b6911319a72900d6ee29460029464593dd53cd1435025e2e5614f60be06104c54b472eb1a68022c9f";
+        let utxo = crate::bytea_to_cardano_utxo(7, &hex::decode(utxo_hex).unwrap());
+
+        assert_eq!(7, crate::cardano_utxo_era(utxo.clone()));
+        assert_eq!(
+            Some(AnyNumeric::from(1744972959u64)),
+            crate::cardano_utxo_lovelace(utxo)
+        );
+    }
+
+    #[pg_test]
+    fn test_cardano_value_arithmetic() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        let script_output = crate::tx_output_value(&tx_cbor, 0).unwrap();
+        let change_output = crate::tx_output_value(&tx_cbor, 1).unwrap();
+        assert!(crate::tx_output_value(&tx_cbor, 2).is_none());
+
+        assert_eq!(
+            AnyNumeric::from(51000000),
+            crate::cardano_value_lovelace(script_output.clone())
+        );
+        assert_eq!(
+            AnyNumeric::from(0x201d16e7cf2u64),
+            crate::cardano_value_asset(
+                script_output.clone(),
+                &hex::decode("f66d78b4a3cb3d37afa0ec36461e51ecbde00f26c8f0a68f94b69880").unwrap(),
+                b"iUSD"
+            )
+        );
+
+        let total = crate::cardano_value_add(script_output.clone(), change_output.clone());
+        assert_eq!(
+            AnyNumeric::from(51000000 + 5637703787u64),
+            crate::cardano_value_lovelace(total.clone())
+        );
+        assert!(crate::cardano_value_le(
+            script_output.clone(),
+            total.clone()
+        ));
+        assert!(!crate::cardano_value_le(
+            total.clone(),
+            script_output.clone()
+        ));
+        assert!(crate::cardano_value_eq(
+            script_output.clone(),
+            crate::cardano_value_sub(total, change_output)
+        ));
+
+        // burns and differences are negative
+        let zero = crate::bytea_to_cardano_value(&[0x00]);
+        let negative = crate::cardano_value_sub(zero.clone(), script_output);
+        assert!(crate::cardano_value_le(negative.clone(), zero.clone()));
+        assert_eq!(
+            AnyNumeric::from(-51000000),
+            crate::cardano_value_lovelace(negative)
+        );
+
+        assert_eq!(Some(zero), crate::tx_mint_value(&tx_cbor));
+    }
+
+    #[pg_test]
+    fn test_asset_subject() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        let policy_id = "f66d78b4a3cb3d37afa0ec36461e51ecbde00f26c8f0a68f94b69880";
+
+        let hex = crate::asset_subject::from_text(&format!("{}69555344", policy_id)).unwrap();
+        let dotted = crate::asset_subject::from_text(&format!("{}.69555344", policy_id)).unwrap();
+        let fingerprint =
+            crate::asset_subject::from_text("asset1rm38ahl5n88c3up6r67y7gn0ffxqwuw7thjxqr")
+                .unwrap();
+        assert_eq!(hex, dotted);
+        assert_eq!(hex.fingerprint, fingerprint.fingerprint);
+
+        for subject in [hex, fingerprint] {
+            assert_eq!(
+                Some(AnyNumeric::from(0x201d16e7cf2u64)),
+                crate::tx_asset_subject_amount_output(&tx_cbor, subject.clone())
+            );
+            assert_eq!(
+                Some(true),
+                crate::tx_has_asset_subject_output(&tx_cbor, subject.clone())
+            );
+            assert_eq!(
+                Some(false),
+                crate::tx_has_asset_subject_mint(&tx_cbor, subject)
+            );
+        }
+
+        // with invalid scripts only the collateral return is produced
+        let invalid_cbor =
+            hex::decode(format!("{}f4a0", &TX_DATA_HEX[..TX_DATA_HEX.len() - 4])).unwrap();
+        assert_eq!(
+            Some(AnyNumeric::from(0)),
+            crate::tx_asset_subject_amount_output(&invalid_cbor, dotted.clone())
+        );
+        assert_eq!(
+            Some(false),
+            crate::tx_has_asset_subject_output(&invalid_cbor, dotted)
+        );
+
+        // CIP-14 test vectors
+        let subject = crate::asset_subject::from_text(
+            "7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373.504154415445",
+        )
+        .unwrap();
+        assert_eq!(
+            "asset13n25uv0yaf5kus35fm2k86cqy60z58d9xmde92",
+            crate::encode_asset_fingerprint(&subject.fingerprint)
+        );
+        assert!(subject.matches(
+            &hex::decode("7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373").unwrap(),
+            b"PATATE"
+        ));
+
+        assert!(
+            crate::asset_subject::from_text(&format!("{}.{}", policy_id, "00".repeat(33))).is_err()
+        );
+        // The last character is changed from the CIP-14 vector above, so this
+        // only fails because the bech32 checksum no longer matches.
+        assert!(
+            crate::asset_subject::from_text("asset1rjklcrnsdzqp65wjgrg55sy9723kw09mlgvlc4")
+                .is_err()
+        );
+    }
+
+    const UTXO_DATA_HEX: &str = "a300581d71071bd7f4b5e059ea90e763467cf559167b21c82ef1cb5fe34fb7a9e501821a030a32c0a3581c1cc1aceaf5c7df55e270864a60600b9f52383fe418164574ffdeeed0a14010581cc0e5564cf5786031d9053f567ec78b8383a0f2bc01318e690e0503f4a14001581cf66d78b4a3cb3d37afa0ec36461e51ecbde00f26c8f0a68f94b69880a144695553441b00000201d16e7cf2028201d818479f0000000000ff";
+
+    #[pg_test]
+    fn test_subject_untyped_literals() {
+        let amount = Some(AnyNumeric::from(0x201d16e7cf2u64));
+        let zero = Some(AnyNumeric::from(0));
+        let subject = "f66d78b4a3cb3d37afa0ec36461e51ecbde00f26c8f0a68f94b69880.69555344";
+        let bytes = subject.replace('.', "");
+
+        let number = |query: String| Spi::get_one::<AnyNumeric>(&query).unwrap();
+        let boolean = |query: String| Spi::get_one::<bool>(&query).unwrap();
+
+        assert_eq!(
+            amount,
+            number(format!(
+                "SELECT tx_subject_amount_output('\\x{TX_DATA_HEX}', '\\x{bytes}')"
+            ))
+        );
+        assert_eq!(
+            zero,
+            number(format!(
+                "SELECT tx_subject_amount_mint('\\x{TX_DATA_HEX}', '\\x{bytes}')"
+            ))
+        );
+        assert_eq!(
+            Some(true),
+            boolean(format!(
+                "SELECT tx_has_subject_output('\\x{TX_DATA_HEX}', '\\x{bytes}')"
+            ))
+        );
+        assert_eq!(
+            Some(false),
+            boolean(format!(
+                "SELECT tx_has_mint_output('\\x{TX_DATA_HEX}', '\\x{bytes}')"
+            ))
+        );
+        assert_eq!(
+            amount,
+            number(format!(
+                "SELECT utxo_subject_amount(6, '\\x{UTXO_DATA_HEX}', '\\x{bytes}')"
+            ))
+        );
+        assert_eq!(
+            amount,
+            number(format!(
+                "SELECT utxo_subject_amount(cardano_utxo(6, '\\x{UTXO_DATA_HEX}'), '\\x{bytes}')"
+            ))
+        );
+
+        for subject in [subject, "asset1rm38ahl5n88c3up6r67y7gn0ffxqwuw7thjxqr"] {
+            assert_eq!(
+                amount,
+                number(format!(
+                    "SELECT tx_asset_subject_amount_output('\\x{TX_DATA_HEX}', '{subject}')"
+                ))
+            );
+            assert_eq!(
+                zero,
+                number(format!(
+                    "SELECT tx_asset_subject_amount_mint('\\x{TX_DATA_HEX}', '{subject}')"
+                ))
+            );
+            assert_eq!(
+                Some(true),
+                boolean(format!(
+                    "SELECT tx_has_asset_subject_output('\\x{TX_DATA_HEX}', '{subject}')"
+                ))
+            );
+            assert_eq!(
+                Some(false),
+                boolean(format!(
+                    "SELECT tx_has_asset_subject_mint('\\x{TX_DATA_HEX}', '{subject}')"
+                ))
+            );
+            assert_eq!(
+                amount,
+                number(format!(
+                    "SELECT utxo_asset_subject_amount(6, '\\x{UTXO_DATA_HEX}', '{subject}')"
+                ))
+            );
+            assert_eq!(
+                amount,
+                number(format!(
+                    "SELECT utxo_asset_subject_amount(cardano_utxo(6, '\\x{UTXO_DATA_HEX}'), '{subject}')"
+                ))
+            );
+        }
+    }
+
+    #[pg_test]
+    fn test_asset_fingerprint() {
+        // CIP-14 test vectors
+        let policy_id =
+            hex::decode("7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373").unwrap();
+        assert_eq!(
+            Some("asset1rjklcrnsdzqp65wjgrg55sy9723kw09mlgvlc3".to_string()),
+            crate::asset_fingerprint(&policy_id, b"")
+        );
+        assert_eq!(
+            Some("asset13n25uv0yaf5kus35fm2k86cqy60z58d9xmde92".to_string()),
+            crate::asset_fingerprint(&policy_id, b"PATATE")
+        );
+
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        assert_eq!(
+            Some(true),
+            crate::tx_has_asset_fingerprint(
+                &tx_cbor,
+                crate::asset_subject::from_text("asset1rm38ahl5n88c3up6r67y7gn0ffxqwuw7thjxqr")
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            Some(false),
+            crate::tx_has_asset_fingerprint(
+                &tx_cbor,
+                crate::asset_subject::from_text("asset1rjklcrnsdzqp65wjgrg55sy9723kw09mlgvlc3")
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            Some(true),
+            Spi::get_one::<bool>(&format!(
+                "SELECT tx_has_asset_fingerprint('\\x{TX_DATA_HEX}', 'asset1rm38ahl5n88c3up6r67y7gn0ffxqwuw7thjxqr')"
+            ))
+            .unwrap()
+        );
+
+        // With invalid scripts only the collateral return, which holds no
+        // assets, is produced.
+        let invalid_cbor =
+            hex::decode(format!("{}f4a0", &TX_DATA_HEX[..TX_DATA_HEX.len() - 4])).unwrap();
+        assert_eq!(
+            Some(false),
+            crate::tx_has_asset_fingerprint(
+                &invalid_cbor,
+                crate::asset_subject::from_text("asset1rm38ahl5n88c3up6r67y7gn0ffxqwuw7thjxqr")
+                    .unwrap()
+            )
+        );
+
+        // the mint of a transaction with invalid scripts never happens
+        let minted =
+            crate::asset_subject::from_text(&format!("{}.4e667431", "22".repeat(28))).unwrap();
+        let tx_hex = TX_CIP25_V2_HEX.replace("a0f5a11902d1", "a0f4a11902d1");
+        assert_eq!(
+            Some(true),
+            crate::tx_has_asset_fingerprint(&hex::decode(TX_CIP25_V2_HEX).unwrap(), minted.clone())
+        );
+        assert_eq!(
+            Some(false),
+            crate::tx_has_asset_fingerprint(&hex::decode(tx_hex).unwrap(), minted)
+        );
+
+        let (.., fingerprints) = crate::tx_outputs(&tx_cbor, true).unwrap().next().unwrap();
+        assert_eq!(
+            serde_json::json!(0x201d16e7cf2u64),
+            fingerprints.0["asset1rm38ahl5n88c3up6r67y7gn0ffxqwuw7thjxqr"]
+        );
+    }
+
+    #[pg_test]
+    fn test_subject_bytes_too_short() {
+        let tx_cbor = hex::decode(TX_DATA_HEX).unwrap();
+        assert_eq!(
+            Some(AnyNumeric::from(0)),
+            crate::tx_subject_amount_output(&tx_cbor, &[0xf6, 0x6d])
+        );
+    }
+
+    #[pg_test]
+    fn test_cardano_value_is_normalized() {
+        // [5, {policy: {"": 0}}] is the same value as 5
+        let cbor =
+            hex::decode("8205a1581c11111111111111111111111111111111111111111111111111111111a14000")
+                .unwrap();
+        assert_eq!(vec![0x05], crate::bytea_to_cardano_value(&cbor).0);
+    }
+
+    #[pg_test(error = "invalid cardano_value: trailing bytes at offset 1")]
+    fn test_cardano_value_rejects_trailing_bytes() {
+        crate::bytea_to_cardano_value(&[0x05, 0x05]);
+    }
+
+    #[pg_test(
+        error = "invalid cardano_value: duplicate policy id 11111111111111111111111111111111111111111111111111111111"
+    )]
+    fn test_cardano_value_rejects_duplicate_policy_ids() {
+        // [5, {policy: {"": 1}, policy: {"": 2}}]
+        let cbor = hex::decode("8205a2581c11111111111111111111111111111111111111111111111111111111a14001581c11111111111111111111111111111111111111111111111111111111a14002").unwrap();
+        crate::bytea_to_cardano_value(&cbor);
+    }
+
+    #[pg_test(
+        error = "invalid cardano_value: duplicate asset 11111111111111111111111111111111111111111111111111111111.41"
+    )]
+    fn test_cardano_value_rejects_duplicate_asset_names() {
+        // [5, {policy: {"A": 1, "A": 2}}]
+        let cbor = hex::decode(
+            "8205a1581c11111111111111111111111111111111111111111111111111111111a2414101414102",
+        )
+        .unwrap();
+        crate::bytea_to_cardano_value(&cbor);
     }
 }
 